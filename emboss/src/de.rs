@@ -0,0 +1,498 @@
+use std::{borrow::Cow, fmt, vec};
+
+use emboss_common::ValueKind;
+
+use serde::de::{
+    self, DeserializeSeed, IntoDeserializer, MapAccess, Visitor,
+    value::{BorrowedStrDeserializer, StrDeserializer},
+};
+
+use crate::{
+    error::{EmbossError, ErrorKind},
+    extract::{DuplicatePolicy, EmbossIter, ExtractOptions, iter_with_options},
+    value::EmbossValue,
+};
+
+/// An error from deserializing embossed metadata into a user type
+#[derive(Debug, Eq, PartialEq)]
+pub enum Error {
+    /// The embossed data itself could not be parsed
+    Extract(EmbossError),
+    /// A field of the type had no embossed key, and no default
+    MissingKey(&'static str),
+    /// The value of an embossed key could not be deserialized into its field
+    InvalidValue {
+        key: String,
+        message: String,
+    },
+    Custom(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Extract(err) => err.fmt(f),
+            Error::MissingKey(key) => {
+                write!(f, "No value was embossed for the key '{}'", key)
+            }
+            Error::InvalidValue { key, message } => {
+                write!(
+                    f,
+                    "The value embossed for '{}' is invalid: {}",
+                    key, message
+                )
+            }
+            Error::Custom(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Extract(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Error::MissingKey(field)
+    }
+}
+
+impl From<EmbossError> for Error {
+    fn from(err: EmbossError) -> Self {
+        Error::Extract(err)
+    }
+}
+
+/// Deserializes the pairs of an embossed section as a map, or as a struct with a field for
+/// each key
+///
+/// Typed values are handed to the visitor as they are, while string values are parsed into
+/// whatever primitive the field asks for. Use serde's `rename` attributes for keys that
+/// aren't valid Rust identifiers, such as `app-version`.
+///
+/// Compressed values are decompressed when the `deflate` feature is enabled, and repeated
+/// keys are handled according to [`ExtractOptions::duplicates`] before any field is filled.
+pub struct Deserializer<'de> {
+    pairs: EmbossIter<'de>,
+    duplicates: DuplicatePolicy,
+}
+
+impl<'de> Deserializer<'de> {
+    pub fn from_bytes(buf: &'de [u8]) -> Self {
+        Self::from_bytes_with_options(buf, &ExtractOptions::default())
+    }
+
+    pub fn from_bytes_with_options(buf: &'de [u8], options: &ExtractOptions) -> Self {
+        Self {
+            pairs: iter_with_options(buf, options),
+            duplicates: options.duplicates,
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let mut pairs = self.pairs;
+        let pairs = std::iter::from_fn(|| pairs.next_decoded(Value::decode))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        visitor.visit_map(PairsAccess {
+            pairs: self.duplicates.apply(pairs)?.into_iter(),
+            pending: None,
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct PairsAccess<'de> {
+    pairs: vec::IntoIter<(&'de str, Value<'de>)>,
+    pending: Option<(&'de str, Value<'de>)>,
+}
+
+impl<'de> MapAccess<'de> for PairsAccess<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let Some((key, value)) = self.pairs.next() else {
+            return Ok(None);
+        };
+
+        self.pending = Some((key, value));
+        seed.deserialize(BorrowedStrDeserializer::new(key))
+            .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (key, value) = self
+            .pending
+            .take()
+            .ok_or_else(|| Error::Custom("A value was requested before its key".to_string()))?;
+
+        // Point any failure at the key whose value caused it
+        seed.deserialize(ValueDeserializer { value })
+            .map_err(|err| match err {
+                Error::Custom(message) => Error::InvalidValue {
+                    key: key.to_string(),
+                    message,
+                },
+                err => err,
+            })
+    }
+}
+
+/// A decoded value, which is owned when it had to be decompressed
+enum Value<'de> {
+    Borrowed(EmbossValue<'de>),
+    String(String),
+    Bytes(Vec<u8>),
+}
+
+impl<'de> Value<'de> {
+    fn decode(kind: ValueKind, raw: Cow<'de, [u8]>) -> Result<Self, EmbossError> {
+        let raw = match raw {
+            Cow::Borrowed(raw) => return EmbossValue::decode(kind, raw).map(Value::Borrowed),
+            Cow::Owned(raw) => raw,
+        };
+
+        // Only strings and bytes borrow from the data, so every other kind can be copied out
+        let value = match EmbossValue::decode(kind, &raw)? {
+            EmbossValue::Str(_) => {
+                let value = String::from_utf8(raw)
+                    .map_err(|err| ErrorKind::InvalidUtf8(err.utf8_error()))?;
+                return Ok(Value::String(value));
+            }
+            EmbossValue::Bytes(_) => return Ok(Value::Bytes(raw)),
+            EmbossValue::U64(value) => EmbossValue::U64(value),
+            EmbossValue::I64(value) => EmbossValue::I64(value),
+            EmbossValue::Bool(value) => EmbossValue::Bool(value),
+            EmbossValue::Timestamp(value) => EmbossValue::Timestamp(value),
+        };
+
+        Ok(Value::Borrowed(value))
+    }
+}
+
+struct ValueDeserializer<'de> {
+    value: Value<'de>,
+}
+
+/// Parse string values into the requested primitive, and hand typed values over as-is
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                let text = match &self.value {
+                    Value::Borrowed(EmbossValue::Str(text)) => *text,
+                    Value::String(text) => text.as_str(),
+                    _ => return self.deserialize_any(visitor),
+                };
+
+                let value = text.parse().map_err(|err| {
+                    Error::Custom(format!("'{}' could not be parsed: {}", text, err))
+                })?;
+                visitor.$visit(value)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let value = match self.value {
+            Value::Borrowed(value) => value,
+            Value::String(value) => return visitor.visit_string(value),
+            Value::Bytes(value) => return visitor.visit_byte_buf(value),
+        };
+
+        match value {
+            EmbossValue::Str(value) => visitor.visit_borrowed_str(value),
+            EmbossValue::U64(value) | EmbossValue::Timestamp(value) => visitor.visit_u64(value),
+            EmbossValue::I64(value) => visitor.visit_i64(value),
+            EmbossValue::Bool(value) => visitor.visit_bool(value),
+            EmbossValue::Bytes(value) => visitor.visit_borrowed_bytes(value),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Borrowed(EmbossValue::Str(value)) => visitor.visit_borrowed_str(value),
+            Value::Borrowed(EmbossValue::Bytes(value)) => match std::str::from_utf8(value) {
+                Ok(value) => visitor.visit_borrowed_str(value),
+                Err(_) => visitor.visit_borrowed_bytes(value),
+            },
+            Value::Borrowed(value) => visitor.visit_string(value.to_string()),
+            Value::String(value) => visitor.visit_string(value),
+            Value::Bytes(value) => match String::from_utf8(value) {
+                Ok(value) => visitor.visit_string(value),
+                Err(err) => visitor.visit_byte_buf(err.into_bytes()),
+            },
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Borrowed(EmbossValue::Str(value)) => {
+                visitor.visit_borrowed_bytes(value.as_bytes())
+            }
+            Value::Borrowed(EmbossValue::Bytes(value)) => visitor.visit_borrowed_bytes(value),
+            Value::Borrowed(value) => visitor.visit_byte_buf(value.to_string().into_bytes()),
+            Value::String(value) => visitor.visit_byte_buf(value.into_bytes()),
+            Value::Bytes(value) => visitor.visit_byte_buf(value),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    // A key that was embossed always has a value
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Unit variants are matched by name against string values
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let value = match &self.value {
+            Value::Borrowed(EmbossValue::Str(value)) => *value,
+            Value::String(value) => value.as_str(),
+            _ => return self.deserialize_any(visitor),
+        };
+
+        let deserializer: StrDeserializer<'_, Error> = value.into_deserializer();
+        de::Deserializer::deserialize_enum(deserializer, name, variants, visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    #[cfg(feature = "deflate")]
+    use emboss_common::Codec;
+
+    use super::*;
+    use crate::{
+        encode::BlockBuilder,
+        extract::{from_bytes, from_bytes_with_options},
+    };
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct BuildInfo<'a> {
+        #[serde(rename = "app-version")]
+        version: String,
+        git_sha: &'a str,
+        build_ts: u64,
+        #[serde(default)]
+        debug: bool,
+        channel: Option<Channel>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Channel {
+        Stable,
+        Nightly,
+    }
+
+    #[test]
+    fn deserialize_struct() {
+        let data = b"\x55\xB0\x77\x1A\x04app-version\x001.2.3\0git_sha\09fceb02\0build_ts\x001620000000\0channel\0nightly\0";
+        let info = from_bytes::<BuildInfo>(data).unwrap();
+        assert_eq!(
+            info,
+            BuildInfo {
+                version: "1.2.3".to_string(),
+                git_sha: "9fceb02",
+                build_ts: 1620000000,
+                debug: false,
+                channel: Some(Channel::Nightly),
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_typed_values() {
+        let block = BlockBuilder::new()
+            .str("app-version", "1.2.3")
+            .str("git_sha", "9fceb02")
+            .value("build_ts", EmbossValue::Timestamp(1620000000))
+            .value("debug", EmbossValue::Bool(true))
+            .str("unused", "ignored")
+            .build()
+            .unwrap();
+
+        let info = from_bytes::<BuildInfo>(&block).unwrap();
+        assert_eq!(info.build_ts, 1620000000);
+        assert!(info.debug);
+        assert_eq!(info.channel, None);
+    }
+
+    #[test]
+    fn deserialize_map() {
+        let data = b"\x55\xB0\x77\x1A\x02foo\0one\0bar\0two\0";
+        let map = from_bytes::<std::collections::BTreeMap<String, String>>(data).unwrap();
+        assert_eq!(map["foo"], "one");
+        assert_eq!(map["bar"], "two");
+    }
+
+    #[test]
+    #[cfg(feature = "deflate")]
+    fn deserialize_compressed() {
+        let changelog = "Fixed a crash on startup. ".repeat(20);
+        let block = BlockBuilder::new()
+            .str("app-version", "1.2.3")
+            .str("git_sha", "9fceb02")
+            .value("build_ts", EmbossValue::Timestamp(1620000000))
+            .str("changelog", &changelog)
+            .compress(Codec::Deflate)
+            .build()
+            .unwrap();
+
+        #[derive(Debug, Deserialize)]
+        struct Release {
+            #[serde(rename = "app-version")]
+            version: String,
+            build_ts: u64,
+            changelog: String,
+        }
+
+        let release = from_bytes::<Release>(&block).unwrap();
+        assert_eq!(release.version, "1.2.3");
+        assert_eq!(release.build_ts, 1620000000);
+        assert_eq!(release.changelog, changelog);
+    }
+
+    #[test]
+    fn deserialize_duplicates() {
+        let data = b"\x55\xB0\x77\x1A\x02channel\0stable\0channel\0nightly\0";
+
+        #[derive(Debug, Deserialize)]
+        struct Release {
+            channel: Channel,
+        }
+
+        let options = ExtractOptions {
+            duplicates: DuplicatePolicy::Error,
+            ..Default::default()
+        };
+        match from_bytes_with_options::<Release>(data, &options) {
+            Err(Error::Extract(err)) => {
+                assert_eq!(*err.kind(), ErrorKind::DuplicateKey("channel".to_string()))
+            }
+            other => panic!("expected a duplicate key error, got {:?}", other),
+        }
+
+        let options = ExtractOptions {
+            duplicates: DuplicatePolicy::LastWins,
+            ..Default::default()
+        };
+        let release = from_bytes_with_options::<Release>(data, &options).unwrap();
+        assert_eq!(release.channel, Channel::Nightly);
+    }
+
+    #[test]
+    fn deserialize_fail_missing_key() {
+        let data = b"\x55\xB0\x77\x1A\x02app-version\x001.2.3\0git_sha\09fceb02\0";
+        assert_eq!(
+            from_bytes::<BuildInfo>(data),
+            Err(Error::MissingKey("build_ts"))
+        );
+    }
+
+    #[test]
+    fn deserialize_fail_invalid_value() {
+        let data =
+            b"\x55\xB0\x77\x1A\x03app-version\x001.2.3\0git_sha\09fceb02\0build_ts\0yesterday\0";
+        match from_bytes::<BuildInfo>(data) {
+            Err(Error::InvalidValue { key, .. }) => assert_eq!(key, "build_ts"),
+            other => panic!("expected an invalid value, got {:?}", other),
+        }
+
+        // Typed values are range checked like any other
+        let block = BlockBuilder::new()
+            .value("small", EmbossValue::U64(300))
+            .build()
+            .unwrap();
+
+        #[derive(Debug, Deserialize)]
+        struct Small {
+            #[allow(dead_code)]
+            small: u8,
+        }
+
+        match from_bytes::<Small>(&block) {
+            Err(Error::InvalidValue { key, .. }) => assert_eq!(key, "small"),
+            other => panic!("expected an invalid value, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn deserialize_fail_extract() {
+        let data = b"\x44\xB0\x77\x1A\x01key\0value\0";
+        match from_bytes::<BuildInfo>(data) {
+            Err(Error::Extract(err)) => {
+                assert_eq!(*err.kind(), ErrorKind::IncorrectLeadingMagic)
+            }
+            other => panic!("expected an extraction error, got {:?}", other),
+        }
+    }
+}
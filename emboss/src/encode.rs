@@ -0,0 +1,378 @@
+use alloc::{
+    borrow::Cow,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use emboss_common::{
+    Codec, Crc32, FLAG_CHECKSUM, FLAG_SIGNATURE, FORMAT_VERSION, LEADING_MAGIC_BYTES,
+    VERSIONED_HEADER_MARKER, ValueKind, entry_tag, is_valid_magic,
+};
+
+use crate::{
+    error::{EmbossError, ErrorKind},
+    value::EmbossValue,
+};
+
+/// Build an embossed block at runtime
+///
+/// The block is laid out exactly as the emboss macros would emit it for the same pairs and
+/// options, so tools that run after linking, or build steps that aren't written in Rust, can
+/// produce sections that [`crate::extract`] reads like any other. Like the macros, a
+/// checksum is included unless it is turned off.
+///
+/// # Example
+///
+/// ```rust
+/// use emboss::{EmbossValue, encode::BlockBuilder};
+///
+/// let block = BlockBuilder::new()
+///     .str("version", "1.2.3")
+///     .value("build", EmbossValue::U64(42))
+///     .build()
+///     .unwrap();
+///
+/// let metadata = emboss::extract::extract_metadata_into_vec(&block).unwrap();
+/// assert_eq!(metadata, vec![("version", "1.2.3"), ("build", "42")]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BlockBuilder {
+    entries: Vec<Entry>,
+    checksum: bool,
+    magic: u32,
+    #[cfg(feature = "deflate")]
+    compress: Option<Codec>,
+    #[cfg(feature = "signatures")]
+    signing_key: Option<ed25519_dalek::SigningKey>,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    key: String,
+    kind: ValueKind,
+    value: Vec<u8>,
+}
+
+impl BlockBuilder {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            checksum: true,
+            magic: LEADING_MAGIC_BYTES,
+            #[cfg(feature = "deflate")]
+            compress: None,
+            #[cfg(feature = "signatures")]
+            signing_key: None,
+        }
+    }
+
+    /// Add a string value
+    pub fn str(&mut self, key: &str, value: &str) -> &mut Self {
+        self.value(key, EmbossValue::Str(value))
+    }
+
+    /// Add a value of any kind, stored as its canonical text unless it is bytes
+    pub fn value(&mut self, key: &str, value: EmbossValue<'_>) -> &mut Self {
+        let bytes = match value {
+            EmbossValue::Bytes(bytes) => bytes.to_vec(),
+            value => value.to_string().into_bytes(),
+        };
+
+        self.entries.push(Entry {
+            key: key.to_string(),
+            kind: value.kind(),
+            value: bytes,
+        });
+        self
+    }
+
+    /// Whether to store a CRC-32 of the block, the same as the macros' `checksum` option
+    pub fn checksum(&mut self, checksum: bool) -> &mut Self {
+        self.checksum = checksum;
+        self
+    }
+
+    /// Start the block with this magic number, the same as the macros' `magic` option
+    pub fn magic(&mut self, magic: u32) -> &mut Self {
+        self.magic = magic;
+        self
+    }
+
+    /// Compress values with the given codec wherever that makes them smaller, the same as
+    /// the macros' `compress` option
+    #[cfg(feature = "deflate")]
+    pub fn compress(&mut self, codec: Codec) -> &mut Self {
+        self.compress = Some(codec);
+        self
+    }
+
+    /// Sign the block with an Ed25519 secret key, the same as the macros' `sign_with_env`
+    /// option
+    #[cfg(feature = "signatures")]
+    pub fn sign_with(&mut self, secret_key: &[u8; 32]) -> &mut Self {
+        self.signing_key = Some(ed25519_dalek::SigningKey::from_bytes(secret_key));
+        self
+    }
+
+    /// Encode the block
+    ///
+    /// Fails if no pairs were added, if the magic number is invalid, or if there are too many
+    /// pairs or a string is too long for its length to fit in the `u32` the format stores it
+    /// as.
+    pub fn build(&self) -> Result<Vec<u8>, EmbossError> {
+        if self.entries.is_empty() {
+            return Err(ErrorKind::EmptyEmbossing.into());
+        } else if !is_valid_magic(self.magic) {
+            return Err(ErrorKind::InvalidMagic(self.magic).into());
+        }
+
+        let count = u32::try_from(self.entries.len()).map_err(|_| ErrorKind::TooManyPairs {
+            limit: u32::MAX as usize,
+        })?;
+
+        // The count and entries are exactly what the checksum and signature cover
+        let mut body = count.to_le_bytes().to_vec();
+        for Entry { key, kind, value } in &self.entries {
+            let (codec, value) = self.encode_value(value);
+            body.push(entry_tag(*kind, codec));
+            push_prefixed(&mut body, key.as_bytes())?;
+            push_prefixed(&mut body, &value)?;
+        }
+
+        let mut flags = 0;
+        if self.checksum {
+            flags |= FLAG_CHECKSUM;
+        }
+
+        let signature = self.sign(&body);
+        if signature.is_some() {
+            flags |= FLAG_SIGNATURE;
+        }
+
+        let mut block = self.magic.to_le_bytes().to_vec();
+        block.extend_from_slice(&[VERSIONED_HEADER_MARKER, FORMAT_VERSION, flags]);
+        block.extend_from_slice(&body[..4]);
+        if self.checksum {
+            let checksum = Crc32::new().update(&body).finish();
+            block.extend_from_slice(&checksum.to_le_bytes());
+        }
+
+        if let Some(signature) = signature {
+            block.extend_from_slice(&signature);
+        }
+
+        block.extend_from_slice(&body[4..]);
+        Ok(block)
+    }
+
+    fn encode_value<'v>(&self, value: &'v [u8]) -> (Codec, Cow<'v, [u8]>) {
+        #[cfg(feature = "deflate")]
+        if let Some(Codec::Deflate) = self.compress {
+            let compressed = miniz_oxide::deflate::compress_to_vec(value, 10);
+            if compressed.len() < value.len() {
+                return (Codec::Deflate, Cow::Owned(compressed));
+            }
+        }
+
+        (Codec::None, Cow::Borrowed(value))
+    }
+
+    #[cfg(feature = "signatures")]
+    fn sign(&self, message: &[u8]) -> Option<[u8; emboss_common::SIGNATURE_LEN]> {
+        use ed25519_dalek::Signer;
+
+        let signing_key = self.signing_key.as_ref()?;
+        Some(signing_key.sign(message).to_bytes())
+    }
+
+    #[cfg(not(feature = "signatures"))]
+    fn sign(&self, _: &[u8]) -> Option<[u8; emboss_common::SIGNATURE_LEN]> {
+        None
+    }
+}
+
+impl Default for BlockBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn push_prefixed(buf: &mut Vec<u8>, bytes: &[u8]) -> Result<(), EmbossError> {
+    let len = u32::try_from(bytes.len()).map_err(|_| ErrorKind::StringTooLong {
+        limit: u32::MAX as usize,
+    })?;
+
+    buf.extend_from_slice(&len.to_le_bytes());
+    buf.extend_from_slice(bytes);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+    use crate::extract::{
+        ExtractOptions, Magic, extract_metadata_into_vec, extract_metadata_into_vec_with_options,
+        extract_typed_metadata_into_vec,
+    };
+
+    fn static_bytes<T>(value: &T) -> &[u8] {
+        unsafe {
+            core::slice::from_raw_parts((value as *const T).cast::<u8>(), core::mem::size_of::<T>())
+        }
+    }
+
+    emboss_macros::emboss_many!(
+        items = [
+            { key = "name", value = "emboss" },
+            { key = "build", value = 42 },
+            { key = "offset", value = -3i32 },
+            { key = "debug", value = true },
+            { key = "built-at", value = Timestamp(1620000000) },
+            { key = "blob", value = b"\x00\x01\x7F" },
+        ],
+        stored_in = "__emboss_encode",
+        export_name = "encode_test"
+    );
+
+    emboss_macros::emboss!(
+        key = "checksum",
+        value = "off",
+        stored_in = "__emboss_encode_unchecked",
+        export_name = "encode_unchecked_test",
+        checksum = false
+    );
+
+    fn typed_builder() -> BlockBuilder {
+        let mut builder = BlockBuilder::new();
+        builder
+            .str("name", "emboss")
+            .value("build", EmbossValue::U64(42))
+            .value("offset", EmbossValue::I64(-3))
+            .value("debug", EmbossValue::Bool(true))
+            .value("built-at", EmbossValue::Timestamp(1620000000))
+            .value("blob", EmbossValue::Bytes(b"\x00\x01\x7F"));
+        builder
+    }
+
+    #[test]
+    fn build_matches_macro() {
+        let block = typed_builder().build().unwrap();
+        assert_eq!(block, static_bytes(&encode_test::EMBOSSED));
+
+        let block = BlockBuilder::new()
+            .str("checksum", "off")
+            .checksum(false)
+            .build()
+            .unwrap();
+        assert_eq!(block, static_bytes(&encode_unchecked_test::EMBOSSED));
+    }
+
+    #[test]
+    fn build_round_trip() {
+        let block = typed_builder().build().unwrap();
+        let metadata = extract_typed_metadata_into_vec(&block).unwrap();
+        assert_eq!(
+            metadata,
+            vec![
+                ("name", EmbossValue::Str("emboss")),
+                ("build", EmbossValue::U64(42)),
+                ("offset", EmbossValue::I64(-3)),
+                ("debug", EmbossValue::Bool(true)),
+                ("built-at", EmbossValue::Timestamp(1620000000)),
+                ("blob", EmbossValue::Bytes(b"\x00\x01\x7F")),
+            ]
+        );
+
+        let block = BlockBuilder::new()
+            .str("version", "1.2.3")
+            .str("", "")
+            .build()
+            .unwrap();
+        let metadata = extract_metadata_into_vec(&block).unwrap();
+        assert_eq!(metadata, vec![("version", "1.2.3"), ("", "")]);
+
+        // The checksum is verified when the block is read back
+        let mut corrupted = block.clone();
+        let patch = corrupted.windows(5).position(|w| w == b"1.2.3").unwrap() + 4;
+        corrupted[patch] = b'4';
+        assert!(matches!(
+            extract_metadata_into_vec(&corrupted).map_err(EmbossError::into_kind),
+            Err(ErrorKind::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn build_fail_empty() {
+        assert_eq!(
+            BlockBuilder::new().build().map_err(EmbossError::into_kind),
+            Err(ErrorKind::EmptyEmbossing)
+        );
+    }
+
+    #[test]
+    fn build_with_magic() {
+        let block = BlockBuilder::new()
+            .str("version", "1.2.3")
+            .magic(0x5EC7_10A7)
+            .build()
+            .unwrap();
+        assert_eq!(block[..4], 0x5EC7_10A7u32.to_le_bytes());
+
+        let options = ExtractOptions {
+            magic: Magic::Exactly(0x5EC7_10A7),
+            ..Default::default()
+        };
+        assert_eq!(
+            extract_metadata_into_vec_with_options(&block, &options).unwrap(),
+            vec![("version", "1.2.3")]
+        );
+
+        assert_eq!(
+            BlockBuilder::new()
+                .str("version", "1.2.3")
+                .magic(0x5EC7_1000)
+                .build()
+                .map_err(EmbossError::into_kind),
+            Err(ErrorKind::InvalidMagic(0x5EC7_1000))
+        );
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn build_compressed() {
+        let license = "Permission is hereby granted, free of charge, ".repeat(8);
+        let block = BlockBuilder::new()
+            .str("license", &license)
+            .str("tiny", "1")
+            .compress(Codec::Deflate)
+            .build()
+            .unwrap();
+        assert!(block.len() < license.len());
+
+        let metadata = crate::extract::extract_decompressed_metadata_into_vec(&block).unwrap();
+        assert_eq!(metadata[0], ("license", Cow::Owned(license)));
+        assert_eq!(metadata[1], ("tiny", Cow::Borrowed("1")));
+    }
+
+    #[cfg(feature = "signatures")]
+    #[test]
+    fn build_signed() {
+        let secret_key = [7; 32];
+        let public_key = ed25519_dalek::SigningKey::from_bytes(&secret_key)
+            .verifying_key()
+            .to_bytes();
+
+        let block = BlockBuilder::new()
+            .str("version", "1.2.3")
+            .sign_with(&secret_key)
+            .build()
+            .unwrap();
+        assert_eq!(crate::extract::verify(&block, &public_key), Ok(()));
+        assert_eq!(
+            extract_metadata_into_vec(&block).unwrap(),
+            vec![("version", "1.2.3")]
+        );
+    }
+}
@@ -1,34 +1,266 @@
-use std::{error::Error, fmt, str::Utf8Error};
+#[cfg(feature = "alloc")]
+use alloc::string::{String, ToString};
+use core::{error::Error, fmt, str::Utf8Error};
+#[cfg(feature = "std")]
+use std::io;
 
+use emboss_common::{Codec, ValueKind};
+
+/// An error from reading or writing embossed data, along with where it was found
+///
+/// What went wrong is described by its [`ErrorKind`]. Errors from parsing a section also
+/// carry the byte offset they were found at, and when they concern a pair, its index and, if
+/// the key could be read, its key. Keys are only kept with the `alloc` feature.
 #[derive(Debug, Eq, PartialEq)]
-pub enum EmbossError {
+pub struct EmbossError {
+    kind: ErrorKind,
+    offset: Option<usize>,
+    pair: Option<usize>,
+    #[cfg(feature = "alloc")]
+    key: Option<String>,
+}
+
+/// The kinds of [`EmbossError`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ErrorKind {
     EmptyEmbossing,
     IncorrectLeadingMagic,
     InvalidEmbossedCString,
     InvalidUtf8(Utf8Error),
+    CountMismatch {
+        expected: usize,
+        found: usize,
+    },
+    DanglingKey,
+    TrailingBytes,
+    TooManyPairs {
+        limit: usize,
+    },
+    StringTooLong {
+        limit: usize,
+    },
+    UnsupportedVersion(u8),
+    Truncated,
+    UnknownValueKind(u8),
+    InvalidTypedValue(ValueKind),
+    ChecksumMismatch {
+        expected: u32,
+        found: u32,
+    },
+    MissingSignature,
+    InvalidSignature,
+    CompressedValue(Codec),
+    InvalidCompressedValue,
+    #[cfg(feature = "alloc")]
+    DuplicateKey(String),
+    InvalidMagic(u32),
+    InvalidPackageNote,
 }
 
+impl EmbossError {
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn into_kind(self) -> ErrorKind {
+        self.kind
+    }
+
+    /// The byte offset in the section where the error was found
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    /// The index of the pair the error concerns, counting from the start of the section
+    pub fn pair(&self) -> Option<usize> {
+        self.pair
+    }
+
+    /// The key of the pair whose value the error concerns
+    pub fn key(&self) -> Option<&str> {
+        #[cfg(feature = "alloc")]
+        return self.key.as_deref();
+
+        #[cfg(not(feature = "alloc"))]
+        None
+    }
+
+    /// Note where the error was found, unless something closer to it already has
+    pub(crate) fn at(mut self, offset: usize) -> Self {
+        self.offset.get_or_insert(offset);
+        self
+    }
+
+    pub(crate) fn in_pair(mut self, pair: usize) -> Self {
+        self.pair.get_or_insert(pair);
+        self
+    }
+
+    #[cfg_attr(not(feature = "alloc"), allow(unused_mut, unused_variables))]
+    pub(crate) fn for_key(mut self, key: &str) -> Self {
+        #[cfg(feature = "alloc")]
+        self.key.get_or_insert_with(|| key.to_string());
+        self
+    }
+}
+
+impl From<ErrorKind> for EmbossError {
+    fn from(kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            offset: None,
+            pair: None,
+            #[cfg(feature = "alloc")]
+            key: None,
+        }
+    }
+}
+
+/// Formats as the kind of error, preceded by where it was found, such as
+/// `At offset 0x2c, in the value of key 'git-sha': invalid utf-8 sequence...`
 impl fmt::Display for EmbossError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut located = false;
+        if let Some(offset) = self.offset {
+            write!(f, "At offset {:#x}", offset)?;
+            located = true;
+        }
+
+        let separator = if located { ", in" } else { "In" };
+        if let Some(key) = self.key() {
+            write!(f, "{} the value of key '{}'", separator, key)?;
+            located = true;
+        } else if let Some(pair) = self.pair {
+            write!(f, "{} pair {}", separator, pair)?;
+            located = true;
+        }
+
+        if located {
+            write!(f, ": ")?;
+        }
+
+        self.kind.fmt(f)
+    }
+}
+
+impl fmt::Display for ErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            EmbossError::EmptyEmbossing => {
+            ErrorKind::EmptyEmbossing => {
                 write!(f, "Empty Embossing")
             }
-            EmbossError::IncorrectLeadingMagic => {
+            ErrorKind::IncorrectLeadingMagic => {
                 write!(
                     f,
                     "Leading bytes did not correspond to a valid Emboss magic number"
                 )
             }
-            EmbossError::InvalidEmbossedCString => {
+            ErrorKind::InvalidEmbossedCString => {
                 write!(
                     f,
                     "No terminating null-byte was found in the embossed string"
                 )
             }
-            EmbossError::InvalidUtf8(err) => err.fmt(f),
+            ErrorKind::InvalidUtf8(err) => err.fmt(f),
+            ErrorKind::CountMismatch { expected, found } => {
+                write!(
+                    f,
+                    "Expected {} embossed pairs but only found {}",
+                    expected, found
+                )
+            }
+            ErrorKind::DanglingKey => {
+                write!(f, "An embossed key was not followed by a value")
+            }
+            ErrorKind::TrailingBytes => {
+                write!(f, "Unexpected bytes were found after the embossed data")
+            }
+            ErrorKind::TooManyPairs { limit } => {
+                write!(f, "More than {} embossed pairs were found", limit)
+            }
+            ErrorKind::StringTooLong { limit } => {
+                write!(
+                    f,
+                    "An embossed string was longer than the limit of {} bytes",
+                    limit
+                )
+            }
+            ErrorKind::UnsupportedVersion(version) => {
+                write!(f, "Unsupported emboss format version {}", version)
+            }
+            ErrorKind::Truncated => {
+                write!(f, "The embossed data ended unexpectedly")
+            }
+            ErrorKind::UnknownValueKind(tag) => {
+                write!(f, "Unknown embossed value kind {}", tag)
+            }
+            ErrorKind::InvalidTypedValue(kind) => {
+                write!(f, "Embossed value is not a valid {:?}", kind)
+            }
+            ErrorKind::ChecksumMismatch { expected, found } => {
+                write!(
+                    f,
+                    "Embossed block checksum {:#010x} did not match the computed {:#010x}",
+                    expected, found
+                )
+            }
+            ErrorKind::MissingSignature => {
+                write!(f, "The embossed block is not signed")
+            }
+            ErrorKind::InvalidSignature => {
+                write!(
+                    f,
+                    "The embossed block's signature is not valid for the given key"
+                )
+            }
+            ErrorKind::CompressedValue(codec) => {
+                write!(
+                    f,
+                    "An embossed value is compressed with {:?} and must be decompressed",
+                    codec
+                )
+            }
+            ErrorKind::InvalidCompressedValue => {
+                write!(f, "An embossed value could not be decompressed")
+            }
+            #[cfg(feature = "alloc")]
+            ErrorKind::DuplicateKey(key) => {
+                write!(f, "The key '{}' was embossed more than once", key)
+            }
+            ErrorKind::InvalidMagic(magic) => {
+                write!(
+                    f,
+                    "The magic number {:#010x} can't be used, as its lowest byte is zero",
+                    magic
+                )
+            }
+            ErrorKind::InvalidPackageNote => {
+                write!(f, "The package metadata note isn't a valid JSON object")
+            }
         }
     }
 }
 
-impl Error for EmbossError {}
+impl Error for EmbossError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            ErrorKind::InvalidUtf8(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Data that ends early is reported as [`io::ErrorKind::UnexpectedEof`], and anything else
+/// as [`io::ErrorKind::InvalidData`]
+#[cfg(feature = "std")]
+impl From<EmbossError> for io::Error {
+    fn from(err: EmbossError) -> Self {
+        let kind = match err.kind {
+            ErrorKind::Truncated => io::ErrorKind::UnexpectedEof,
+            _ => io::ErrorKind::InvalidData,
+        };
+
+        io::Error::new(kind, err)
+    }
+}
@@ -1,21 +1,72 @@
-use std::{collections::HashMap, ffi::CStr};
+#[cfg(feature = "alloc")]
+use alloc::{
+    borrow::Cow,
+    boxed::Box,
+    collections::BTreeSet,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+#[cfg(feature = "alloc")]
+use core::ops::Range;
+use core::{ffi::CStr, iter::FusedIterator};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
 
-use emboss_common::LEADING_MAGIC_BYTES;
+use emboss_common::{
+    CODEC_SHIFT, Codec, Crc32, FLAG_CHECKSUM, FLAG_SIGNATURE, FORMAT_VERSION, LEADING_MAGIC_BYTES,
+    SIGNATURE_LEN, VERSIONED_HEADER_MARKER, ValueKind,
+};
 
-use crate::error::EmbossError;
+#[cfg(feature = "alloc")]
+use crate::metadata::EmbossedMetadata;
+use crate::{
+    error::{EmbossError, ErrorKind},
+    value::EmbossValue,
+};
 
 /// Extract embossed metadata from the raw bytes in a section
 ///
-/// The complete binary format is as follows:
+/// Two versions of the binary format can be read. The current one, emitted by the
+/// emboss macros, is as follows:
 ///
 /// ```text
-/// [MAGIC_BYTES (4 bytes)][COUNT (1 byte)][KEY_1]\0[VALUE_1]\0[KEY_2]\0[VALUE_2]\0...
+/// [MAGIC_BYTES (4 bytes)][MARKER (1 byte)][VERSION (1 byte)][FLAGS (1 byte)][COUNT (4 bytes)]
+///     [CHECKSUM (4 bytes, optional)][SIGNATURE (64 bytes, optional)]
+///     [KIND_1 (1 byte)][KEY_1_LEN (4 bytes)][KEY_1][VALUE_1_LEN (4 bytes)][VALUE_1]...
 /// ```
 ///
 /// Where:
-/// - `MAGIC_BYTES` are the 4 bytes defined in `emboss_common::LEADING_MAGIC_BYTES`
-/// - `COUNT` is a single byte indicating how many key-value pairs are present
-/// - Each key and value is a null-terminated UTF-8 string
+/// - `MAGIC_BYTES` are the 4 bytes defined in `emboss_common::LEADING_MAGIC_BYTES`, or the
+///   `magic` the block was embossed with, in little-endian order
+/// - `MARKER` is always `emboss_common::VERSIONED_HEADER_MARKER`
+/// - `VERSION` is `emboss_common::FORMAT_VERSION`
+/// - `FLAGS` is a bit set; `emboss_common::FLAG_CHECKSUM` means `CHECKSUM` is present
+/// - `COUNT` is a little-endian `u32` indicating how many key-value pairs are present
+/// - `CHECKSUM` is a little-endian CRC-32 over `COUNT` and every entry that follows it
+/// - `SIGNATURE`, present when `FLAGS` has `emboss_common::FLAG_SIGNATURE`, is an Ed25519
+///   signature over the same bytes as `CHECKSUM`
+/// - `KIND_N` is the `emboss_common::ValueKind` of the value that follows in its low 4 bits,
+///   and the `emboss_common::Codec` the value is compressed with in its high 4 bits
+/// - Each key and value is a UTF-8 string, prefixed by its length as a little-endian `u32`
+///
+/// Typed values (integers, booleans, and timestamps) are stored as their canonical text,
+/// so every value can be read as a string. Byte values are stored as-is, and can only be
+/// read as a string if they happen to be valid UTF-8. Use [`iter_typed`] to get values
+/// back as an [`EmbossValue`] instead.
+///
+/// Binaries built with older versions of emboss use the v1 format instead:
+///
+/// ```text
+/// [MAGIC_BYTES (4 bytes)][COUNT (1 byte)][KEY_1]\0[VALUE_1]\0[KEY_2]\0[VALUE_2]\0...
+/// ```
+///
+/// Where `COUNT` is a single, non-zero byte and each key and value is a null-terminated
+/// UTF-8 string. Older versions wrote the magic in the target's native byte order, so with
+/// [`Magic::Default`] a byte-swapped magic from a big-endian target is accepted as well.
+///
+/// A section can contain several such blocks back to back, optionally separated by zero
+/// padding. The pairs of every block are returned in order.
 ///
 /// # Example
 ///
@@ -35,8 +86,22 @@ use crate::error::EmbossError;
 /// assert_eq!(key, "build-id");
 /// assert_eq!(value, "abc123");
 /// ```
+#[cfg(feature = "alloc")]
 pub fn extract_metadata_into_vec(buf: &[u8]) -> Result<Vec<(&str, &str)>, EmbossError> {
-    extract_metadata(buf)
+    iter(buf).collect()
+}
+
+/// Extract embossed metadata into a vector, using the given options
+///
+/// See [`ExtractOptions`] for what can be configured, including how keys that appear more
+/// than once are handled.
+#[cfg(feature = "alloc")]
+pub fn extract_metadata_into_vec_with_options<'a>(
+    buf: &'a [u8],
+    options: &ExtractOptions,
+) -> Result<Vec<(&'a str, &'a str)>, EmbossError> {
+    let pairs = iter_with_options(buf, options).collect::<Result<Vec<_>, _>>()?;
+    options.duplicates.apply(pairs)
 }
 
 /// Extract embossed metadata into a HashMap for key-based lookups
@@ -68,110 +133,3158 @@ pub fn extract_metadata_into_vec(buf: &[u8]) -> Result<Vec<(&str, &str)>, Emboss
 ///
 /// - The data doesn't start with the correct magic bytes (`IncorrectLeadingMagic`)
 /// - The data contains no key-value pairs (`EmptyEmbossing`)
+/// - The data ends partway through a header or string (`Truncated`)
 /// - Any string in the data is missing a null terminator (`InvalidEmbossedCString`)
 /// - Any string in the data isn't valid UTF-8 (`InvalidUtf8`)
+/// - A block holds fewer pairs than its count claims (`CountMismatch`)
+/// - A key is not followed by a value (`DanglingKey`)
+///
+/// When a key appears more than once, the last value wins. Use
+/// [`extract_metadata_into_hashmap_with_options`] to pick a different [`DuplicatePolicy`].
+#[cfg(feature = "std")]
 pub fn extract_metadata_into_hashmap(buf: &[u8]) -> Result<HashMap<&str, &str>, EmbossError> {
-    let items = extract_metadata(buf)?
-        .iter()
-        .fold(HashMap::new(), |mut map, (key, value)| {
-            map.insert(*key, *value);
-            map
+    extract_metadata_into_hashmap_with_options(buf, &ExtractOptions::default())
+}
+
+/// Extract embossed metadata into a HashMap, using the given options
+///
+/// See [`ExtractOptions`] for what can be configured. A HashMap can only hold one value per
+/// key, so [`DuplicatePolicy::CollectAll`] keeps the last value here, the same as
+/// [`DuplicatePolicy::LastWins`].
+#[cfg(feature = "std")]
+pub fn extract_metadata_into_hashmap_with_options<'a>(
+    buf: &'a [u8],
+    options: &ExtractOptions,
+) -> Result<HashMap<&'a str, &'a str>, EmbossError> {
+    Ok(extract_metadata_into_vec_with_options(buf, options)?
+        .into_iter()
+        .collect())
+}
+
+/// Extract embossed metadata into an [`EmbossedMetadata`] that owns its data
+///
+/// Pairs keep their embossing order, including any duplicate keys. With the `deflate`
+/// feature, compressed values are decompressed rather than rejected.
+#[cfg(feature = "alloc")]
+pub fn extract_metadata_into_owned(buf: &[u8]) -> Result<EmbossedMetadata, EmbossError> {
+    extract_metadata_into_owned_with_options(buf, &ExtractOptions::default())
+}
+
+/// Extract embossed metadata into an [`EmbossedMetadata`], using the given options
+#[cfg(feature = "alloc")]
+pub fn extract_metadata_into_owned_with_options(
+    buf: &[u8],
+    options: &ExtractOptions,
+) -> Result<EmbossedMetadata, EmbossError> {
+    #[cfg(feature = "deflate")]
+    let pairs = iter_decompressed_with_options(buf, options).collect::<Result<Vec<_>, _>>()?;
+
+    #[cfg(not(feature = "deflate"))]
+    let pairs = iter_with_options(buf, options).collect::<Result<Vec<_>, _>>()?;
+
+    Ok(options.duplicates.apply(pairs)?.into_iter().collect())
+}
+
+/// Extract embossed metadata along with where each part of it sits in the section
+///
+/// Each block comes back with the spans of its header fields, and each pair with the spans
+/// of its key and value. Spans are byte ranges relative to the start of `buf` and cover only
+/// the contents of a string, not its length prefix or terminator, so a tool can highlight
+/// or patch a value in place. As with [`extract_metadata_into_blocks_with_options`],
+/// [`ExtractOptions::duplicates`] applies to each block on its own.
+///
+/// # Example
+///
+/// ```rust
+/// let data = b"\x55\xB0\x77\x1A\x01version\x001.2.3\0";
+///
+/// let blocks = emboss::extract::extract_with_spans(data).unwrap();
+/// assert_eq!(blocks[0].header.magic, 0..4);
+/// assert_eq!(blocks[0].header.count, 4..5);
+///
+/// let pair = &blocks[0].pairs[0];
+/// assert_eq!((pair.key, pair.value), ("version", "1.2.3"));
+/// assert_eq!(pair.key_span, 5..12);
+/// assert_eq!(&data[pair.value_span.clone()], b"1.2.3");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn extract_with_spans(buf: &[u8]) -> Result<Vec<SpannedBlock<'_>>, EmbossError> {
+    extract_with_spans_with_options(buf, &ExtractOptions::default())
+}
+
+/// Extract embossed metadata along with where each part of it sits, using the given options
+#[cfg(feature = "alloc")]
+pub fn extract_with_spans_with_options<'a>(
+    buf: &'a [u8],
+    options: &ExtractOptions,
+) -> Result<Vec<SpannedBlock<'a>>, EmbossError> {
+    let mut iter = iter_with_options(buf, options);
+    let mut blocks: Vec<SpannedBlock<'a>> = Vec::new();
+    while let Some(entry) = iter.next_entry() {
+        let entry = entry?.uncompressed()?;
+        let Entry {
+            key,
+            value,
+            key_offset,
+            value_offset,
+            ..
+        } = entry;
+
+        let header = iter.header_spans();
+        let block = match blocks.last_mut() {
+            Some(block) if block.header == header => block,
+            _ => {
+                blocks.push(SpannedBlock {
+                    header,
+                    pairs: Vec::new(),
+                });
+                blocks.last_mut().expect("a block was just pushed")
+            }
+        };
+
+        block.pairs.push(SpannedPair {
+            key,
+            value: entry.value_str()?,
+            key_span: key_offset..key_offset + key.len(),
+            value_span: value_offset..value_offset + value.len(),
         });
-    Ok(items)
+    }
+
+    for block in &mut blocks {
+        let pairs = block.pairs.drain(..).map(|pair| (pair.key, pair)).collect();
+        block.pairs = options
+            .duplicates
+            .apply(pairs)?
+            .into_iter()
+            .map(|(_, pair)| pair)
+            .collect();
+    }
+
+    Ok(blocks)
+}
+
+/// A block returned by [`extract_with_spans`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg(feature = "alloc")]
+pub struct SpannedBlock<'a> {
+    pub header: HeaderSpans,
+    pub pairs: Vec<SpannedPair<'a>>,
+}
+
+/// Where each field of a block header sits in the section
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg(feature = "alloc")]
+pub struct HeaderSpans {
+    /// The leading magic, which is where the block starts
+    pub magic: Range<usize>,
+
+    /// The number of pairs in the block, a single byte in the v1 format
+    pub count: Range<usize>,
+
+    /// The CRC-32 of the block, if it has one
+    pub checksum: Option<Range<usize>>,
+
+    /// The Ed25519 signature of the block, if it was signed
+    pub signature: Option<Range<usize>>,
 }
 
-// TODO(Matt): This is internal with a transparent passthrough via extract_metadata_into_vec
-//  simply because we want generators to stabilize so that we can just yield pairs instead
-//  of allocating the Vec
-//  Once we can do that on stable Rust, we'll refactor this function and then expose it in
-//  the public API
-fn extract_metadata(buf: &[u8]) -> Result<Vec<(&str, &str)>, EmbossError> {
-    let leading_magic_matches = buf
-        .first_chunk::<4>()
-        .map(|chunk| u32::from_le_bytes(*chunk))
-        .filter(|bytes| *bytes == LEADING_MAGIC_BYTES)
-        .is_some();
+/// A key-value pair returned by [`extract_with_spans`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg(feature = "alloc")]
+pub struct SpannedPair<'a> {
+    pub key: &'a str,
+    pub value: &'a str,
+    pub key_span: Range<usize>,
+    pub value_span: Range<usize>,
+}
+
+/// Extract every pair that can still be decoded from a damaged section
+///
+/// Rather than failing on the first problem, parsing carries on past it and notes it as a
+/// [`Diagnostic`] along with its byte offset in `buf`. Invalid UTF-8 is replaced with
+/// `U+FFFD`, a v1 string without a terminator runs to the end of `buf`, and a block whose
+/// checksum doesn't match is read anyway. When the entries of a block can no longer be
+/// told apart, the rest of it is skipped and parsing resumes at the next leading magic.
+///
+/// [`ExtractOptions::duplicates`] applies across the whole section, except that with
+/// [`DuplicatePolicy::Error`] every pair is kept and each repeated key is noted as a
+/// diagnostic instead. With the `deflate` feature compressed values are decompressed.
+///
+/// # Example
+///
+/// ```rust
+/// use std::borrow::Cow;
+///
+/// let data = b"\x55\xB0\x77\x1A\x02version\x001.2.\xFF\0commit\09fceb02\0";
+///
+/// let extraction = emboss::extract::extract_metadata_lossy(data);
+/// assert_eq!(extraction.pairs[0], (Cow::Borrowed("version"), Cow::Borrowed("1.2.\u{FFFD}")));
+/// assert_eq!(extraction.pairs[1], (Cow::Borrowed("commit"), Cow::Borrowed("9fceb02")));
+/// assert_eq!(extraction.diagnostics[0].offset, 17);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn extract_metadata_lossy(buf: &[u8]) -> LossyExtraction<'_> {
+    extract_metadata_lossy_with_options(buf, &ExtractOptions::default())
+}
+
+/// Extract every pair that can still be decoded from a damaged section, using the given
+/// options
+#[cfg(feature = "alloc")]
+pub fn extract_metadata_lossy_with_options<'a>(
+    buf: &'a [u8],
+    options: &ExtractOptions,
+) -> LossyExtraction<'a> {
+    EmbossIter::with_options(buf, options).recover()
+}
+
+/// A key-value pair recovered by [`extract_metadata_lossy`]
+#[cfg(feature = "alloc")]
+pub type LossyPair<'a> = (Cow<'a, str>, Cow<'a, str>);
+
+/// The pairs recovered from a section by [`extract_metadata_lossy`], and what went wrong
+#[derive(Debug, Default, Eq, PartialEq)]
+#[cfg(feature = "alloc")]
+pub struct LossyExtraction<'a> {
+    /// Every pair that could be decoded, in embossing order
+    pub pairs: Vec<LossyPair<'a>>,
 
-    if !leading_magic_matches {
-        return Err(EmbossError::IncorrectLeadingMagic);
+    /// Every problem found along the way, in the order they were found
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[cfg(feature = "alloc")]
+impl LossyExtraction<'_> {
+    fn diagnose(&mut self, offset: usize, error: impl Into<EmbossError>) {
+        self.diagnostics.push(Diagnostic::new(offset, error));
     }
 
-    let item_count = buf[4] as usize;
-    if item_count == 0 {
-        return Err(EmbossError::EmptyEmbossing);
+    /// Apply a duplicate policy to the recovered pairs, whose keys start at `key_offsets`
+    fn apply_duplicates(&mut self, duplicates: DuplicatePolicy, key_offsets: &[usize]) {
+        if duplicates == DuplicatePolicy::Error {
+            let mut seen = BTreeSet::new();
+            for ((key, _), offset) in self.pairs.iter().zip(key_offsets) {
+                if !seen.insert(key.as_ref()) {
+                    let error = ErrorKind::DuplicateKey(key.to_string());
+                    self.diagnostics.push(Diagnostic::new(*offset, error));
+                }
+            }
+
+            return;
+        }
+
+        // Only the error policy can fail
+        if let Ok(pairs) = duplicates.apply(core::mem::take(&mut self.pairs)) {
+            self.pairs = pairs;
+        }
     }
+}
 
-    let mut content_buf = &buf[5..];
-    let mut items = Vec::with_capacity(item_count);
+/// A problem found while recovering pairs from a damaged section
+#[derive(Debug, Eq, PartialEq)]
+#[cfg(feature = "alloc")]
+pub struct Diagnostic {
+    /// The byte offset in the section where the problem was found
+    pub offset: usize,
 
-    let mut current_key = None;
-    while !content_buf.is_empty() {
-        let str_slice = CStr::from_bytes_until_nul(content_buf)
-            .map_err(|_| EmbossError::InvalidEmbossedCString)?;
+    /// What the problem was, which carries the same offset
+    pub error: EmbossError,
+}
 
-        let str_slice = str_slice.to_str().map_err(EmbossError::InvalidUtf8)?;
+#[cfg(feature = "alloc")]
+impl Diagnostic {
+    fn new(offset: usize, error: impl Into<EmbossError>) -> Self {
+        let error = error.into().at(offset);
+        Self {
+            offset: error.offset().unwrap_or(offset),
+            error,
+        }
+    }
 
-        if let Some(key) = current_key.take() {
-            items.push((key, str_slice));
-        } else {
-            current_key = Some(str_slice);
+    fn for_key(self, key: &str) -> Self {
+        Self {
+            error: self.error.for_key(key),
+            ..self
         }
+    }
+}
 
-        content_buf = &content_buf[str_slice.len() + 1..];
+#[cfg(feature = "alloc")]
+impl core::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.error.fmt(f)
     }
+}
 
-    Ok(items)
+/// Lazily iterate over the embossed key-value pairs in a section
+///
+/// Unlike `extract_metadata_into_vec`, nothing is allocated; each pair borrows directly
+/// from `buf`. The header is validated on the first call to `next`, and the iterator
+/// stops after yielding the first error it encounters.
+///
+/// # Example
+///
+/// ```rust
+/// let data = b"\x55\xB0\x77\x1A\x02app-version\0v1.0.0\0build-id\0abc123\0";
+///
+/// let mut pairs = emboss::extract::iter(data);
+/// assert_eq!(pairs.next(), Some(Ok(("app-version", "v1.0.0"))));
+/// assert_eq!(pairs.next(), Some(Ok(("build-id", "abc123"))));
+/// assert_eq!(pairs.next(), None);
+/// ```
+pub fn iter(buf: &[u8]) -> EmbossIter<'_> {
+    EmbossIter::new(buf)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Lazily iterate over the embossed key-value pairs in a section, using the given options
+///
+/// # Example
+///
+/// ```rust
+/// use emboss::{error::ErrorKind, extract::ExtractOptions};
+///
+/// let data = b"\x55\xB0\x77\x1A\x01key\0value\0garbage";
+/// let options = ExtractOptions {
+///     strict: true,
+///     ..Default::default()
+/// };
+///
+/// let mut pairs = emboss::extract::iter_with_options(data, &options);
+/// assert_eq!(pairs.next(), Some(Ok(("key", "value"))));
+/// let err = pairs.next().unwrap().unwrap_err();
+/// assert_eq!(*err.kind(), ErrorKind::TrailingBytes);
+/// assert_eq!(err.offset(), Some(15));
+/// ```
+pub fn iter_with_options<'a>(buf: &'a [u8], options: &ExtractOptions) -> EmbossIter<'a> {
+    EmbossIter::with_options(buf, options)
+}
 
-    #[test]
-    fn extract_basic() {
-        let data = b"\x55\xB0\x77\x1A\x01key\0value\0";
-        let metadata = extract_metadata_into_hashmap(data).unwrap();
-        let value = metadata.get("key").unwrap();
-        assert_eq!(*value, "value")
+/// Options that control how strictly embossed data is parsed
+///
+/// Parsing never panics, whatever the input. When the data comes from an untrusted binary,
+/// the limits bound how much work a single section can cause.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ExtractOptions {
+    /// Reject any bytes after the last block other than zero padding
+    ///
+    /// By default, parsing simply stops at the first thing after a block that isn't another
+    /// block, which is convenient when handed a buffer that extends past the section.
+    pub strict: bool,
+
+    /// The maximum number of key-value pairs to accept across all blocks
+    pub max_pairs: Option<usize>,
+
+    /// The maximum length, in bytes and excluding the terminator, of any key or value
+    pub max_string_len: Option<usize>,
+
+    /// Don't verify the CRC-32 of blocks that carry one
+    pub skip_checksums: bool,
+
+    /// What the collection helpers do with a key that appears more than once
+    ///
+    /// The iterators always yield every pair, whatever the policy.
+    pub duplicates: DuplicatePolicy,
+
+    /// Which magic numbers mark the start of a block
+    pub magic: Magic,
+}
+
+/// The magic numbers accepted at the start of a block
+///
+/// Projects that emboss with their own `magic` can read only their own blocks, and skip
+/// over anyone else's when scanning memory or firmware images.
+///
+/// # Example
+///
+/// ```rust
+/// use emboss::extract::{ExtractOptions, Magic};
+///
+/// let data = b"\xA7\x10\xC7\x5E\x01key\0value\0";
+/// let options = ExtractOptions {
+///     magic: Magic::Exactly(0x5EC7_10A7),
+///     ..Default::default()
+/// };
+///
+/// let pairs = emboss::extract::iter_with_options(data, &options);
+/// assert_eq!(pairs.collect::<Result<Vec<_>, _>>().unwrap(), vec![("key", "value")]);
+/// assert!(emboss::extract::iter(data).next().unwrap().is_err());
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Magic {
+    /// Only [`LEADING_MAGIC_BYTES`], which the macros use unless given another
+    #[default]
+    Default,
+    /// Only the given magic number
+    Exactly(u32),
+    /// Any of the given magic numbers
+    AnyOf(&'static [u32]),
+}
+
+impl Magic {
+    /// Whether the four bytes are an accepted magic number
+    ///
+    /// Custom magic numbers have only ever been written little-endian. The default one is
+    /// accepted in either byte order, as older versions wrote it in the target's native order.
+    pub fn matches(&self, chunk: &[u8; 4]) -> bool {
+        let is = |magic: u32| u32::from_le_bytes(*chunk) == magic;
+
+        match self {
+            Magic::Default => {
+                is(LEADING_MAGIC_BYTES) || u32::from_be_bytes(*chunk) == LEADING_MAGIC_BYTES
+            }
+            Magic::Exactly(magic) => is(*magic),
+            Magic::AnyOf(magics) => magics.iter().copied().any(is),
+        }
     }
+}
 
-    #[test]
-    fn extract_fail_incorrect_magic() {
-        let data = b"\x44\xB0\x77\x1A\x01key\0value\0";
-        if let Err(error) = extract_metadata_into_hashmap(data) {
-            return assert_eq!(error, EmbossError::IncorrectLeadingMagic);
+/// How a key that appears more than once in a section is handled
+///
+/// Keys repeat when several blocks emboss the same key, or when a single embossing names a
+/// key twice. The policy applies to every `extract_*` collection helper, and to each block
+/// on its own for [`extract_metadata_into_blocks_with_options`] and
+/// [`extract_with_spans_with_options`]. [`extract_metadata_lossy_with_options`] notes
+/// repeated keys as diagnostics rather than failing.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum DuplicatePolicy {
+    /// Keep every pair, in embossing order
+    ///
+    /// Look up every value of a key with [`EmbossedMetadata::get_all`].
+    #[default]
+    CollectAll,
+    /// Fail with [`ErrorKind::DuplicateKey`]
+    Error,
+    /// Keep only the first pair with each key
+    FirstWins,
+    /// Keep only the last pair with each key
+    LastWins,
+}
+
+#[cfg(feature = "alloc")]
+impl DuplicatePolicy {
+    pub(crate) fn apply<K: AsRef<str>, V>(
+        self,
+        pairs: Vec<(K, V)>,
+    ) -> Result<Vec<(K, V)>, EmbossError> {
+        if self == DuplicatePolicy::CollectAll {
+            return Ok(pairs);
         }
 
-        panic!("expected an error to be returned")
+        // Walk backwards for last-wins, so the pair that is kept is always seen first
+        let order: Box<dyn Iterator<Item = usize>> = match self {
+            DuplicatePolicy::LastWins => Box::new((0..pairs.len()).rev()),
+            _ => Box::new(0..pairs.len()),
+        };
+
+        let mut seen = BTreeSet::new();
+        let mut keep = vec![false; pairs.len()];
+        for index in order {
+            let key = pairs[index].0.as_ref();
+            if seen.insert(key) {
+                keep[index] = true;
+            } else if self == DuplicatePolicy::Error {
+                return Err(ErrorKind::DuplicateKey(key.to_string()).into());
+            }
+        }
+
+        Ok(pairs
+            .into_iter()
+            .zip(keep)
+            .filter_map(|(pair, keep)| keep.then_some(pair))
+            .collect())
     }
+}
 
-    #[test]
-    fn extract_fail_on_empty_data() {
-        let data = b"\x55\xB0\x77\x1A\x00";
-        if let Err(error) = extract_metadata_into_hashmap(data) {
-            return assert_eq!(error, EmbossError::EmptyEmbossing);
+/// Iterator over the key-value pairs of an embossed section, created by [`iter`]
+///
+/// A section may hold several blocks back to back, which happens when the linker merges
+/// the embossings of multiple crates into the same section. Each block's `COUNT` decides
+/// where it ends, any zero padding between blocks is skipped, and the pairs of every
+/// block are yielded in order. Use [`blocks`] to keep the pairs grouped by block.
+#[derive(Clone, Debug)]
+pub struct EmbossIter<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    state: IterState,
+    single_block: bool,
+    options: ExtractOptions,
+    yielded: usize,
+    format: Format,
+    block_start: usize,
+    block_flags: u8,
+    /// Where `buf` starts within the section, for blocks parsed on their own
+    base: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Format {
+    NullTerminated,
+    LengthPrefixed,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum IterState {
+    FirstHeader,
+    NextHeader,
+    Pairs { count: usize, remaining: usize },
+    Done,
+}
+
+impl<'a> EmbossIter<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self::with_options(buf, &ExtractOptions::default())
+    }
+
+    pub fn with_options(buf: &'a [u8], options: &ExtractOptions) -> Self {
+        Self {
+            buf,
+            pos: 0,
+            state: IterState::FirstHeader,
+            single_block: false,
+            options: *options,
+            yielded: 0,
+            format: Format::LengthPrefixed,
+            block_start: 0,
+            block_flags: 0,
+            base: 0,
         }
+    }
 
-        panic!("expected an error to be returned")
+    fn single_block(buf: &'a [u8], options: &ExtractOptions) -> Self {
+        Self {
+            single_block: true,
+            ..Self::with_options(buf, options)
+        }
     }
 
-    #[test]
-    fn extract_fail_invalid_utf8() {
-        let data = &[0x55, 0xB0, 0x77, 0x1A, 0x01, 0xB0, 0x55, 0x0, 0x49, 0x0];
-        if let Err(error) = extract_metadata_into_hashmap(data) {
-            return assert!(matches!(error, EmbossError::InvalidUtf8(_)));
+    fn remaining_buf(&self) -> &'a [u8] {
+        &self.buf[self.pos..]
+    }
+
+    fn at_magic(&self) -> bool {
+        self.remaining_buf()
+            .first_chunk::<4>()
+            .filter(|chunk| self.options.magic.matches(chunk))
+            .is_some()
+    }
+
+    /// Move to the next thing that looks like a block header at or after `from`
+    #[cfg(feature = "alloc")]
+    fn skip_to_next_block(&mut self, from: usize) -> bool {
+        let next = self.buf.get(from..).and_then(|buf| {
+            buf.windows(4).position(|window| {
+                window
+                    .first_chunk::<4>()
+                    .is_some_and(|chunk| self.options.magic.matches(chunk))
+            })
+        });
+
+        match next {
+            Some(offset) => {
+                self.pos = from + offset;
+                self.state = IterState::NextHeader;
+                true
+            }
+            None => false,
         }
+    }
 
-        panic!("expected an error to be returned")
+    fn skip_padding(&mut self) {
+        let padding = self
+            .remaining_buf()
+            .iter()
+            .take_while(|byte| **byte == 0)
+            .count();
+        self.pos += padding;
     }
 
-    #[test]
-    fn extract_fail_invalid_c_string() {
-        let data = b"\x55\xB0\x77\x1A\x01foo\0bar";
-        if let Err(error) = extract_metadata_into_hashmap(data) {
-            return assert_eq!(error, EmbossError::InvalidEmbossedCString);
+    fn read_header(&mut self) -> Result<usize, EmbossError> {
+        let start = self.section_offset();
+        self.parse_header().map_err(|err| err.at(start))
+    }
+
+    fn parse_header(&mut self) -> Result<usize, EmbossError> {
+        if !self.at_magic() {
+            return Err(ErrorKind::IncorrectLeadingMagic.into());
         }
 
-        panic!("expected an error to be returned")
+        let (format, flags, count, header_len) = match self.buf.get(self.pos + 4) {
+            None => return Err(ErrorKind::Truncated.into()),
+            Some(&VERSIONED_HEADER_MARKER) => {
+                // An unversioned header ending here is just an empty v1 block
+                let version = match self.buf.get(self.pos + 5) {
+                    Some(version) => *version,
+                    None => return Err(ErrorKind::EmptyEmbossing.into()),
+                };
+
+                if version != FORMAT_VERSION {
+                    return Err(ErrorKind::UnsupportedVersion(version).into());
+                }
+
+                let flags = *self.buf.get(self.pos + 6).ok_or(ErrorKind::Truncated)?;
+                let count_bytes = self
+                    .buf
+                    .get(self.pos + 7..)
+                    .and_then(|buf| buf.first_chunk::<4>())
+                    .ok_or(ErrorKind::Truncated)?;
+                let count = u32::from_le_bytes(*count_bytes) as usize;
+
+                let header_len = versioned_header_len(flags);
+                if self.buf.len() < self.pos + header_len {
+                    return Err(ErrorKind::Truncated.into());
+                }
+
+                if flags & FLAG_CHECKSUM != 0 && !self.options.skip_checksums {
+                    let expected = self.buf[self.pos + 11..]
+                        .first_chunk::<4>()
+                        .map(|checksum| u32::from_le_bytes(*checksum))
+                        .ok_or(ErrorKind::Truncated)?;
+
+                    let start = self.pos + header_len;
+                    self.verify_checksum(count_bytes, start, count, expected)
+                        .map_err(|err| err.at(self.base + self.pos + 11))?;
+                }
+
+                (Format::LengthPrefixed, flags, count, header_len)
+            }
+            Some(count) => (Format::NullTerminated, 0, *count as usize, 5),
+        };
+
+        if count == 0 {
+            return Err(ErrorKind::EmptyEmbossing.into());
+        }
+
+        // Bail before parsing anything if the block can't possibly fit within the limit
+        if let Some(limit) = self.options.max_pairs {
+            if count > limit.saturating_sub(self.yielded) {
+                return Err(ErrorKind::TooManyPairs { limit }.into());
+            }
+        }
+
+        self.format = format;
+        self.block_start = self.section_offset();
+        self.block_flags = flags;
+        self.pos += header_len;
+        Ok(count)
+    }
+
+    /// Where each field of the header of the current block sits
+    #[cfg(feature = "alloc")]
+    fn header_spans(&self) -> HeaderSpans {
+        let start = self.block_start;
+        let span = |offset: usize, len: usize| start + offset..start + offset + len;
+
+        match self.format {
+            Format::NullTerminated => HeaderSpans {
+                magic: span(0, 4),
+                count: span(4, 1),
+                checksum: None,
+                signature: None,
+            },
+            Format::LengthPrefixed => {
+                let has_checksum = self.block_flags & FLAG_CHECKSUM != 0;
+                let signature_offset = if has_checksum { 15 } else { 11 };
+
+                HeaderSpans {
+                    magic: span(0, 4),
+                    count: span(7, 4),
+                    checksum: has_checksum.then(|| span(11, 4)),
+                    signature: (self.block_flags & FLAG_SIGNATURE != 0)
+                        .then(|| span(signature_offset, SIGNATURE_LEN)),
+                }
+            }
+        }
+    }
+
+    /// Checks the CRC-32 of a v2 block whose entries start at `start`
+    ///
+    /// The end of the block is found by hopping over the length prefixes of its entries. If
+    /// the block is cut short, the checksum is left alone and parsing reports the truncation.
+    fn verify_checksum(
+        &self,
+        count_bytes: &[u8; 4],
+        start: usize,
+        count: usize,
+        expected: u32,
+    ) -> Result<(), EmbossError> {
+        let read_len = |pos: usize| {
+            self.buf
+                .get(pos..)
+                .and_then(|buf| buf.first_chunk::<4>())
+                .map(|len| u32::from_le_bytes(*len) as usize)
+        };
+        let skip_prefixed = |pos: usize| {
+            let end = read_len(pos)?.checked_add(pos + 4)?;
+            (end <= self.buf.len()).then_some(end)
+        };
+
+        let mut end = start;
+        for _ in 0..count {
+            let key_end = match skip_prefixed(end + 1) {
+                Some(key_end) => key_end,
+                None => return Ok(()),
+            };
+            end = match skip_prefixed(key_end) {
+                Some(value_end) => value_end,
+                None => return Ok(()),
+            };
+        }
+
+        let found = Crc32::new()
+            .update(count_bytes)
+            .update(&self.buf[start..end])
+            .finish();
+
+        if found != expected {
+            return Err(ErrorKind::ChecksumMismatch { expected, found }.into());
+        }
+
+        Ok(())
+    }
+
+    fn read_bytes(&mut self) -> Result<&'a [u8], EmbossError> {
+        match self.format {
+            Format::NullTerminated => self.read_c_bytes(),
+            Format::LengthPrefixed => self.read_prefixed_bytes(),
+        }
+    }
+
+    fn read_prefixed_bytes(&mut self) -> Result<&'a [u8], EmbossError> {
+        let len = self
+            .remaining_buf()
+            .first_chunk::<4>()
+            .map(|len| u32::from_le_bytes(*len) as usize)
+            .ok_or(ErrorKind::Truncated)?;
+
+        if let Some(limit) = self.options.max_string_len {
+            if len > limit {
+                return Err(ErrorKind::StringTooLong { limit }.into());
+            }
+        }
+
+        let bytes = self
+            .remaining_buf()
+            .get(4..)
+            .and_then(|buf| buf.get(..len))
+            .ok_or(ErrorKind::Truncated)?;
+        self.pos += 4 + len;
+
+        Ok(bytes)
+    }
+
+    fn read_c_bytes(&mut self) -> Result<&'a [u8], EmbossError> {
+        let mut search_buf = self.remaining_buf();
+        if let Some(limit) = self.options.max_string_len {
+            // Only look as far as the longest allowed string and its terminator
+            if let Some(limited_buf) = search_buf.get(..=limit) {
+                if !limited_buf.contains(&0) {
+                    return Err(ErrorKind::StringTooLong { limit }.into());
+                }
+
+                search_buf = limited_buf;
+            }
+        }
+
+        let bytes = CStr::from_bytes_until_nul(search_buf)
+            .map_err(|_| ErrorKind::InvalidEmbossedCString)?
+            .to_bytes();
+        self.pos += bytes.len() + 1;
+
+        Ok(bytes)
+    }
+
+    fn read_kind(&mut self) -> Result<(ValueKind, Codec), EmbossError> {
+        if let Format::NullTerminated = self.format {
+            return Ok((ValueKind::Str, Codec::None));
+        }
+
+        let tag = *self.remaining_buf().first().ok_or(ErrorKind::Truncated)?;
+        let kind = ValueKind::from_tag(tag & ((1 << CODEC_SHIFT) - 1));
+        let codec = Codec::from_tag(tag >> CODEC_SHIFT);
+        let (kind, codec) = kind.zip(codec).ok_or(ErrorKind::UnknownValueKind(tag))?;
+        self.pos += 1;
+
+        Ok((kind, codec))
+    }
+
+    fn read_entry(&mut self) -> Result<Entry<'a>, EmbossError> {
+        let index = self.yielded;
+        let tag_offset = self.section_offset();
+        let (kind, codec) = self
+            .read_kind()
+            .map_err(|err| err.at(tag_offset).in_pair(index))?;
+
+        let key_offset = self.string_offset();
+        let key = self
+            .read_bytes()
+            .and_then(|key| {
+                core::str::from_utf8(key).map_err(|err| {
+                    EmbossError::from(ErrorKind::InvalidUtf8(err))
+                        .at(key_offset + err.valid_up_to())
+                })
+            })
+            .map_err(|err| err.at(key_offset).in_pair(index))?;
+
+        // A key that is the last string in the buffer has no value to pair with
+        if self.remaining_buf().is_empty() {
+            return Err(EmbossError::from(ErrorKind::DanglingKey)
+                .at(self.section_offset())
+                .in_pair(index)
+                .for_key(key));
+        }
+
+        let value_offset = self.string_offset();
+        let value = self
+            .read_bytes()
+            .map_err(|err| err.at(value_offset).in_pair(index).for_key(key))?;
+
+        Ok(Entry {
+            key,
+            kind,
+            codec,
+            value,
+            index,
+            key_offset,
+            value_offset,
+        })
+    }
+
+    /// The offset of the current position from the start of the section
+    fn section_offset(&self) -> usize {
+        self.base + self.pos
+    }
+
+    /// Where the contents of the string about to be read start in the section, past any
+    /// length prefix
+    fn string_offset(&self) -> usize {
+        match self.format {
+            Format::NullTerminated => self.section_offset(),
+            Format::LengthPrefixed => self.section_offset() + 4,
+        }
+    }
+
+    fn check_trailing(&self) -> Result<(), EmbossError> {
+        if self.options.strict && !self.remaining_buf().is_empty() {
+            return Err(EmbossError::from(ErrorKind::TrailingBytes).at(self.section_offset()));
+        }
+
+        Ok(())
+    }
+
+    fn advance_header(&mut self) -> Result<(), EmbossError> {
+        match self.state {
+            IterState::FirstHeader => {
+                let count = self.read_header()?;
+                self.state = IterState::Pairs {
+                    count,
+                    remaining: count,
+                };
+            }
+            IterState::NextHeader if self.single_block => self.state = IterState::Done,
+            IterState::NextHeader => {
+                self.skip_padding();
+
+                // Unless parsing strictly, anything that isn't another block is left alone,
+                // the same way trailing bytes after a single block always have been
+                self.state = if self.at_magic() {
+                    let count = self.read_header()?;
+                    IterState::Pairs {
+                        count,
+                        remaining: count,
+                    }
+                } else {
+                    self.check_trailing()?;
+                    IterState::Done
+                };
+            }
+            IterState::Pairs { .. } | IterState::Done => {}
+        }
+
+        Ok(())
+    }
+
+    fn next_entry(&mut self) -> Option<Result<Entry<'a>, EmbossError>> {
+        loop {
+            if let Err(err) = self.advance_header() {
+                self.state = IterState::Done;
+                return Some(Err(err));
+            }
+
+            match self.state {
+                IterState::Pairs { remaining: 0, .. } => self.state = IterState::NextHeader,
+                IterState::Pairs { count, remaining } => {
+                    if self.remaining_buf().is_empty() {
+                        self.state = IterState::Done;
+                        let err = EmbossError::from(ErrorKind::CountMismatch {
+                            expected: count,
+                            found: count - remaining,
+                        });
+                        return Some(Err(err.at(self.section_offset())));
+                    }
+
+                    let item = self.read_entry();
+                    self.state = match item {
+                        Ok(_) => IterState::Pairs {
+                            count,
+                            remaining: remaining - 1,
+                        },
+                        Err(_) => IterState::Done,
+                    };
+                    self.yielded += 1;
+
+                    return Some(item);
+                }
+                IterState::Done => return None,
+                IterState::FirstHeader | IterState::NextHeader => {}
+            }
+        }
+    }
+
+    /// Walk every block, keeping whatever can still be decoded and noting what can't
+    ///
+    /// Bad UTF-8, a missing terminator, or a checksum mismatch only cost the string or block
+    /// they're found in. Anything that loses track of where entries start abandons the rest
+    /// of the block, and parsing picks up again at the next leading magic.
+    #[cfg(feature = "alloc")]
+    fn recover(mut self) -> LossyExtraction<'a> {
+        let mut extraction = LossyExtraction::default();
+        let mut key_offsets = Vec::new();
+        loop {
+            let header = match self.advance_header() {
+                Err(err) if matches!(err.kind(), ErrorKind::ChecksumMismatch { .. }) => {
+                    extraction.diagnose(self.section_offset(), err);
+
+                    let skip_checksums = self.options.skip_checksums;
+                    self.options.skip_checksums = true;
+                    let header = self.advance_header();
+                    self.options.skip_checksums = skip_checksums;
+                    header
+                }
+                header => header,
+            };
+
+            if let Err(err) = header {
+                extraction.diagnose(self.section_offset(), err);
+                if !self.skip_to_next_block(self.pos + 1) {
+                    break;
+                }
+
+                continue;
+            }
+
+            match self.state {
+                IterState::Pairs { remaining: 0, .. } => self.state = IterState::NextHeader,
+                IterState::Pairs { count, remaining } => {
+                    if self.remaining_buf().is_empty() {
+                        extraction.diagnose(
+                            self.section_offset(),
+                            ErrorKind::CountMismatch {
+                                expected: count,
+                                found: count - remaining,
+                            },
+                        );
+                        break;
+                    }
+
+                    let start = self.pos;
+                    match self.read_lossy_entry(&mut extraction.diagnostics) {
+                        Ok(pair) => {
+                            if let Some((key_offset, pair)) = pair {
+                                key_offsets.push(key_offset);
+                                extraction.pairs.push(pair);
+                            }
+
+                            self.state = IterState::Pairs {
+                                count,
+                                remaining: remaining - 1,
+                            };
+                            self.yielded += 1;
+                        }
+                        Err(err) => {
+                            extraction.diagnose(self.section_offset(), err);
+                            if !self.skip_to_next_block(start + 1) {
+                                break;
+                            }
+                        }
+                    }
+                }
+                IterState::Done => {
+                    // Whatever follows the last block may still hide another one further on
+                    let trailing = self.section_offset();
+                    if !self.skip_to_next_block(self.pos) {
+                        break;
+                    }
+
+                    extraction.diagnose(trailing, ErrorKind::TrailingBytes);
+                }
+                IterState::FirstHeader | IterState::NextHeader => {}
+            }
+        }
+
+        extraction.apply_duplicates(self.options.duplicates, &key_offsets);
+        extraction
+    }
+
+    /// Read an entry along with the offset of its key, or `None` if it is framed correctly but
+    /// its value can't be recovered
+    ///
+    /// An error means the entry couldn't be framed at all.
+    #[cfg(feature = "alloc")]
+    fn read_lossy_entry(
+        &mut self,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Result<Option<(usize, LossyPair<'a>)>, EmbossError> {
+        let tag_offset = self.section_offset();
+        let codec = match self.read_kind() {
+            Ok((_, codec)) => Some(codec),
+            Err(err) => match *err.kind() {
+                ErrorKind::UnknownValueKind(tag) => {
+                    // The length prefixes still frame the entry, so a bad tag only loses its
+                    // value if the codec can't be made out either
+                    diagnostics.push(Diagnostic::new(tag_offset, err));
+                    self.pos += 1;
+                    Codec::from_tag(tag >> CODEC_SHIFT)
+                }
+                _ => return Err(err),
+            },
+        };
+
+        let (key_offset, key) = self.read_lossy_bytes(diagnostics)?;
+        let key = lossy_str(Cow::Borrowed(key), key_offset, diagnostics);
+
+        if self.remaining_buf().is_empty() {
+            return Err(EmbossError::from(ErrorKind::DanglingKey).at(self.section_offset()));
+        }
+
+        let first = diagnostics.len();
+        let value = self.read_lossy_value(codec, diagnostics)?;
+
+        // Point anything wrong with the value at its key
+        let value_diagnostics = diagnostics.split_off(first);
+        diagnostics.extend(
+            value_diagnostics
+                .into_iter()
+                .map(|diagnostic| diagnostic.for_key(&key)),
+        );
+
+        Ok(value.map(|value| (key_offset, (key, value))))
+    }
+
+    #[cfg(feature = "alloc")]
+    fn read_lossy_value(
+        &mut self,
+        codec: Option<Codec>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Result<Option<Cow<'a, str>>, EmbossError> {
+        let (value_offset, value) = self.read_lossy_bytes(diagnostics)?;
+        let Some(codec) = codec else {
+            return Ok(None);
+        };
+
+        match decompress(codec, value, &self.options) {
+            Ok(value) => Ok(Some(lossy_str(value, value_offset, diagnostics))),
+            Err(error) => {
+                diagnostics.push(Diagnostic::new(value_offset, error));
+                Ok(None)
+            }
+        }
+    }
+
+    /// Read a key or value along with its offset, keeping the rest of the buffer if a v1
+    /// string is never terminated
+    #[cfg(feature = "alloc")]
+    fn read_lossy_bytes(
+        &mut self,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Result<(usize, &'a [u8]), EmbossError> {
+        let start = self.section_offset();
+        let offset = self.string_offset();
+        match self.read_bytes() {
+            Ok(bytes) => Ok((offset, bytes)),
+            Err(err) if *err.kind() == ErrorKind::InvalidEmbossedCString => {
+                diagnostics.push(Diagnostic::new(start, err));
+
+                let bytes = self.remaining_buf();
+                self.pos = self.buf.len();
+                Ok((start, bytes))
+            }
+            Err(err) => Err(err.at(offset)),
+        }
+    }
+
+    /// Convert this into an iterator over typed values
+    pub fn typed(self) -> TypedEmbossIter<'a> {
+        TypedEmbossIter { inner: self }
+    }
+
+    /// Convert this into an iterator that decompresses values as needed
+    #[cfg(feature = "deflate")]
+    pub fn decompressed(self) -> DecompressedEmbossIter<'a> {
+        DecompressedEmbossIter { inner: self }
+    }
+
+    /// Decompress the value of the next pair, and decode it along with its kind
+    ///
+    /// Used by the deserializer, which needs typed values that may have to be owned. Any
+    /// error from `decode` is pointed at the pair, like the errors of the other iterators.
+    #[cfg(feature = "serde")]
+    pub(crate) fn next_decoded<T>(
+        &mut self,
+        decode: impl FnOnce(ValueKind, Cow<'a, [u8]>) -> Result<T, EmbossError>,
+    ) -> Option<Result<(&'a str, T), EmbossError>> {
+        let options = self.options;
+        let item = self.next_entry()?.and_then(|entry| {
+            let value = decompress(entry.codec, entry.value, &options)
+                .and_then(|value| decode(entry.kind, value))
+                .map_err(|err| entry.locate(err))?;
+            Ok((entry.key, value))
+        });
+
+        if item.is_err() {
+            self.state = IterState::Done;
+        }
+
+        Some(item)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Entry<'a> {
+    key: &'a str,
+    kind: ValueKind,
+    codec: Codec,
+    value: &'a [u8],
+    index: usize,
+    // Only spans need the offset of the key
+    #[cfg_attr(not(feature = "alloc"), allow(dead_code))]
+    key_offset: usize,
+    value_offset: usize,
+}
+
+impl<'a> Entry<'a> {
+    /// Compressed values have to be decompressed into owned data, so they can't be borrowed
+    fn uncompressed(self) -> Result<Self, EmbossError> {
+        if self.codec != Codec::None {
+            return Err(self.locate(ErrorKind::CompressedValue(self.codec)));
+        }
+
+        Ok(self)
+    }
+
+    fn value_str(&self) -> Result<&'a str, EmbossError> {
+        core::str::from_utf8(self.value).map_err(|err| {
+            let offset = self.value_offset + err.valid_up_to();
+            self.locate(EmbossError::from(ErrorKind::InvalidUtf8(err)).at(offset))
+        })
+    }
+
+    /// Point an error about the value at this entry
+    fn locate(&self, err: impl Into<EmbossError>) -> EmbossError {
+        err.into()
+            .at(self.value_offset)
+            .in_pair(self.index)
+            .for_key(self.key)
+    }
+}
+
+impl<'a> Iterator for EmbossIter<'a> {
+    type Item = Result<(&'a str, &'a str), EmbossError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.next_entry()?.and_then(|entry| {
+            let entry = entry.uncompressed()?;
+            Ok((entry.key, entry.value_str()?))
+        });
+
+        if item.is_err() {
+            self.state = IterState::Done;
+        }
+
+        Some(item)
+    }
+}
+
+impl FusedIterator for EmbossIter<'_> {}
+
+/// Lazily iterate over the embossed pairs in a section, with each value parsed according
+/// to its kind
+///
+/// Values embossed in the v1 format, or by `emboss_env!`, are always strings.
+///
+/// # Example
+///
+/// ```rust
+/// use emboss::EmbossValue;
+///
+/// let data = b"\x55\xB0\x77\x1A\x00\x02\x00\x02\0\0\0\
+///     \x01\x05\0\0\0build\x02\0\0\x0042\
+///     \x03\x05\0\0\0debug\x04\0\0\0true";
+///
+/// let mut pairs = emboss::extract::iter_typed(data);
+/// assert_eq!(pairs.next(), Some(Ok(("build", EmbossValue::U64(42)))));
+/// assert_eq!(pairs.next(), Some(Ok(("debug", EmbossValue::Bool(true)))));
+/// assert_eq!(pairs.next(), None);
+/// ```
+pub fn iter_typed(buf: &[u8]) -> TypedEmbossIter<'_> {
+    iter(buf).typed()
+}
+
+/// Lazily iterate over the typed pairs in a section, using the given options
+pub fn iter_typed_with_options<'a>(buf: &'a [u8], options: &ExtractOptions) -> TypedEmbossIter<'a> {
+    iter_with_options(buf, options).typed()
+}
+
+/// Extract typed embossed metadata into a vector, preserving the embossing order
+#[cfg(feature = "alloc")]
+pub fn extract_typed_metadata_into_vec(
+    buf: &[u8],
+) -> Result<Vec<(&str, EmbossValue<'_>)>, EmbossError> {
+    extract_typed_metadata_into_vec_with_options(buf, &ExtractOptions::default())
+}
+
+/// Extract typed embossed metadata into a vector, using the given options
+#[cfg(feature = "alloc")]
+pub fn extract_typed_metadata_into_vec_with_options<'a>(
+    buf: &'a [u8],
+    options: &ExtractOptions,
+) -> Result<Vec<(&'a str, EmbossValue<'a>)>, EmbossError> {
+    let pairs = iter_typed_with_options(buf, options).collect::<Result<Vec<_>, _>>()?;
+    options.duplicates.apply(pairs)
+}
+
+/// Extract typed embossed metadata into a HashMap for key-based lookups
+#[cfg(feature = "std")]
+pub fn extract_typed_metadata_into_hashmap(
+    buf: &[u8],
+) -> Result<HashMap<&str, EmbossValue<'_>>, EmbossError> {
+    extract_typed_metadata_into_hashmap_with_options(buf, &ExtractOptions::default())
+}
+
+/// Extract typed embossed metadata into a HashMap, using the given options
+#[cfg(feature = "std")]
+pub fn extract_typed_metadata_into_hashmap_with_options<'a>(
+    buf: &'a [u8],
+    options: &ExtractOptions,
+) -> Result<HashMap<&'a str, EmbossValue<'a>>, EmbossError> {
+    Ok(extract_typed_metadata_into_vec_with_options(buf, options)?
+        .into_iter()
+        .collect())
+}
+
+/// Iterator over the typed pairs of an embossed section, created by [`iter_typed`]
+#[derive(Clone, Debug)]
+pub struct TypedEmbossIter<'a> {
+    inner: EmbossIter<'a>,
+}
+
+impl<'a> Iterator for TypedEmbossIter<'a> {
+    type Item = Result<(&'a str, EmbossValue<'a>), EmbossError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.inner.next_entry()?.and_then(|entry| {
+            let entry = entry.uncompressed()?;
+            let value =
+                EmbossValue::decode(entry.kind, entry.value).map_err(|err| entry.locate(err))?;
+            Ok((entry.key, value))
+        });
+
+        if item.is_err() {
+            self.inner.state = IterState::Done;
+        }
+
+        Some(item)
+    }
+}
+
+impl FusedIterator for TypedEmbossIter<'_> {}
+
+/// Lazily iterate over the pairs in a section, decompressing any compressed values
+///
+/// Values embossed with `compress` can't be borrowed from the section, so they're returned
+/// as owned strings while every other value is still borrowed. The borrowing iterators,
+/// such as [`iter`], fail with [`ErrorKind::CompressedValue`] when they reach one.
+#[cfg(feature = "deflate")]
+pub fn iter_decompressed(buf: &[u8]) -> DecompressedEmbossIter<'_> {
+    iter(buf).decompressed()
+}
+
+/// Lazily iterate over the pairs in a section, decompressing values with the given options
+///
+/// The `max_string_len` limit also applies to the decompressed length of each value.
+#[cfg(feature = "deflate")]
+pub fn iter_decompressed_with_options<'a>(
+    buf: &'a [u8],
+    options: &ExtractOptions,
+) -> DecompressedEmbossIter<'a> {
+    iter_with_options(buf, options).decompressed()
+}
+
+/// Extract embossed metadata into a vector, decompressing values as needed
+#[cfg(feature = "deflate")]
+pub fn extract_decompressed_metadata_into_vec(
+    buf: &[u8],
+) -> Result<Vec<(&str, Cow<'_, str>)>, EmbossError> {
+    extract_decompressed_metadata_into_vec_with_options(buf, &ExtractOptions::default())
+}
+
+/// Extract embossed metadata into a vector, decompressing values with the given options
+#[cfg(feature = "deflate")]
+pub fn extract_decompressed_metadata_into_vec_with_options<'a>(
+    buf: &'a [u8],
+    options: &ExtractOptions,
+) -> Result<Vec<(&'a str, Cow<'a, str>)>, EmbossError> {
+    let pairs = iter_decompressed_with_options(buf, options).collect::<Result<Vec<_>, _>>()?;
+    options.duplicates.apply(pairs)
+}
+
+/// Extract embossed metadata into a HashMap, decompressing values as needed
+#[cfg(all(feature = "deflate", feature = "std"))]
+pub fn extract_decompressed_metadata_into_hashmap(
+    buf: &[u8],
+) -> Result<HashMap<&str, Cow<'_, str>>, EmbossError> {
+    extract_decompressed_metadata_into_hashmap_with_options(buf, &ExtractOptions::default())
+}
+
+/// Extract embossed metadata into a HashMap, decompressing values with the given options
+#[cfg(all(feature = "deflate", feature = "std"))]
+pub fn extract_decompressed_metadata_into_hashmap_with_options<'a>(
+    buf: &'a [u8],
+    options: &ExtractOptions,
+) -> Result<HashMap<&'a str, Cow<'a, str>>, EmbossError> {
+    Ok(
+        extract_decompressed_metadata_into_vec_with_options(buf, options)?
+            .into_iter()
+            .collect(),
+    )
+}
+
+/// Iterator over the pairs of an embossed section that decompresses values, created by
+/// [`iter_decompressed`]
+#[cfg(feature = "deflate")]
+#[derive(Clone, Debug)]
+pub struct DecompressedEmbossIter<'a> {
+    inner: EmbossIter<'a>,
+}
+
+#[cfg(feature = "deflate")]
+impl<'a> Iterator for DecompressedEmbossIter<'a> {
+    type Item = Result<(&'a str, Cow<'a, str>), EmbossError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let options = self.inner.options;
+        let item = self.inner.next_entry()?.and_then(|entry| {
+            let value = match decompress(entry.codec, entry.value, &options) {
+                Ok(Cow::Borrowed(_)) => Cow::Borrowed(entry.value_str()?),
+                Ok(Cow::Owned(value)) => Cow::Owned(
+                    String::from_utf8(value)
+                        .map_err(|err| entry.locate(ErrorKind::InvalidUtf8(err.utf8_error())))?,
+                ),
+                Err(err) => return Err(entry.locate(err)),
+            };
+
+            Ok((entry.key, value))
+        });
+
+        if item.is_err() {
+            self.inner.state = IterState::Done;
+        }
+
+        Some(item)
+    }
+}
+
+#[cfg(feature = "deflate")]
+impl FusedIterator for DecompressedEmbossIter<'_> {}
+
+/// Decode a string, replacing invalid UTF-8 and noting where it was found
+#[cfg(feature = "alloc")]
+fn lossy_str<'a>(
+    bytes: Cow<'a, [u8]>,
+    offset: usize,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Cow<'a, str> {
+    match bytes {
+        Cow::Borrowed(bytes) => match core::str::from_utf8(bytes) {
+            Ok(value) => Cow::Borrowed(value),
+            Err(err) => {
+                let offset = offset + err.valid_up_to();
+                diagnostics.push(Diagnostic::new(offset, ErrorKind::InvalidUtf8(err)));
+                String::from_utf8_lossy(bytes)
+            }
+        },
+        // Offsets within a decompressed value would mean nothing, so point at the value
+        Cow::Owned(bytes) => match String::from_utf8(bytes) {
+            Ok(value) => Cow::Owned(value),
+            Err(err) => {
+                let error = ErrorKind::InvalidUtf8(err.utf8_error());
+                diagnostics.push(Diagnostic::new(offset, error));
+                Cow::Owned(String::from_utf8_lossy(err.as_bytes()).into_owned())
+            }
+        },
+    }
+}
+
+#[cfg(all(feature = "alloc", not(feature = "deflate")))]
+fn decompress<'a>(
+    codec: Codec,
+    value: &'a [u8],
+    _: &ExtractOptions,
+) -> Result<Cow<'a, [u8]>, EmbossError> {
+    match codec {
+        Codec::None => Ok(Cow::Borrowed(value)),
+        codec => Err(ErrorKind::CompressedValue(codec).into()),
+    }
+}
+
+#[cfg(feature = "deflate")]
+fn decompress<'a>(
+    codec: Codec,
+    value: &'a [u8],
+    options: &ExtractOptions,
+) -> Result<Cow<'a, [u8]>, EmbossError> {
+    use miniz_oxide::inflate::{TINFLStatus, decompress_to_vec_with_limit};
+
+    match codec {
+        Codec::None => Ok(Cow::Borrowed(value)),
+        Codec::Deflate => {
+            // Bound the output, as a tiny compressed value can expand enormously
+            let limit = options.max_string_len.unwrap_or(usize::MAX);
+            decompress_to_vec_with_limit(value, limit)
+                .map(Cow::Owned)
+                .map_err(|err| match err.status {
+                    TINFLStatus::HasMoreOutput => ErrorKind::StringTooLong { limit }.into(),
+                    _ => ErrorKind::InvalidCompressedValue.into(),
+                })
+        }
+    }
+}
+
+/// Iterate over each embossed block in a section
+///
+/// Every block is fully validated before it is yielded, so the pairs of an
+/// [`EmbossBlock`] can be iterated again without re-checking for errors.
+///
+/// # Example
+///
+/// ```rust
+/// // Two blocks, as the linker would lay them out, with a byte of padding in between
+/// let data = b"\x55\xB0\x77\x1A\x01foo\0one\0\0\x55\xB0\x77\x1A\x01bar\0two\0";
+///
+/// let mut blocks = emboss::extract::blocks(data).map(|block| block.unwrap());
+/// assert_eq!(blocks.next().unwrap().iter().next(), Some(Ok(("foo", "one"))));
+/// assert_eq!(blocks.next().unwrap().iter().next(), Some(Ok(("bar", "two"))));
+/// assert!(blocks.next().is_none());
+/// ```
+pub fn blocks(buf: &[u8]) -> Blocks<'_> {
+    blocks_with_options(buf, &ExtractOptions::default())
+}
+
+/// Iterate over each embossed block in a section, using the given options
+pub fn blocks_with_options<'a>(buf: &'a [u8], options: &ExtractOptions) -> Blocks<'a> {
+    Blocks {
+        buf,
+        pos: 0,
+        done: false,
+        options: *options,
+        yielded: 0,
+    }
+}
+
+/// Iterate over the blocks of a section that may start with padding, or hold nothing else
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "illumos",
+    target_os = "solaris"
+))]
+pub(crate) fn padded_blocks_with_options<'a>(
+    buf: &'a [u8],
+    options: &ExtractOptions,
+) -> Blocks<'a> {
+    // Past the first block, padding is skipped anyway
+    let padding = buf.iter().take_while(|byte| **byte == 0).count();
+    Blocks {
+        pos: padding,
+        done: buf.is_empty(),
+        ..blocks_with_options(buf, options)
+    }
+}
+
+/// Extract embossed metadata, keeping the pairs of each block together
+///
+/// See [`blocks`] for how blocks are located within the section.
+#[cfg(feature = "alloc")]
+pub fn extract_metadata_into_blocks(buf: &[u8]) -> Result<Vec<Vec<(&str, &str)>>, EmbossError> {
+    extract_metadata_into_blocks_with_options(buf, &ExtractOptions::default())
+}
+
+/// Extract embossed metadata grouped by block, using the given options
+#[cfg(feature = "alloc")]
+pub fn extract_metadata_into_blocks_with_options<'a>(
+    buf: &'a [u8],
+    options: &ExtractOptions,
+) -> Result<Vec<Vec<(&'a str, &'a str)>>, EmbossError> {
+    blocks_with_options(buf, options)
+        .map(|block| {
+            let pairs = block?.iter().collect::<Result<Vec<_>, _>>()?;
+            options.duplicates.apply(pairs)
+        })
+        .collect()
+}
+
+/// Verify that every block in a section was signed with the secret key matching an Ed25519
+/// public key
+///
+/// Blocks are signed by passing `sign_with_env` to the emboss macros. Fails with
+/// [`ErrorKind::MissingSignature`] if any block is unsigned, and with
+/// [`ErrorKind::InvalidSignature`] if any signature doesn't match its block.
+///
+/// Like the checksum, a signature covers the block's `COUNT` and its entries, but not its
+/// header: the magic, format version and flags aren't signed, so they can be changed without
+/// invalidating it. Only the key-value pairs are vouched for.
+#[cfg(feature = "signatures")]
+pub fn verify(buf: &[u8], public_key: &[u8; 32]) -> Result<(), EmbossError> {
+    blocks(buf).try_for_each(|block| block?.verify(public_key))
+}
+
+/// Deserialize the pairs in a section into a type implementing `serde::Deserialize`
+///
+/// Each key maps onto the struct field of the same name, so keys like `app-version` need a
+/// `#[serde(rename)]` or `#[serde(rename_all = "kebab-case")]`. String values are parsed
+/// into numeric and boolean fields, and a field with no embossed key fails with
+/// [`crate::de::Error::MissingKey`] unless it is an `Option` or has a default.
+///
+/// # Example
+///
+/// ```rust
+/// #[derive(serde::Deserialize)]
+/// struct BuildInfo {
+///     #[serde(rename = "app-version")]
+///     version: String,
+///     build_ts: u64,
+/// }
+///
+/// let data = b"\x55\xB0\x77\x1A\x02app-version\x001.2.3\0build_ts\x001620000000\0";
+/// let info: BuildInfo = emboss::extract::from_bytes(data).unwrap();
+///
+/// assert_eq!(info.version, "1.2.3");
+/// assert_eq!(info.build_ts, 1620000000);
+/// ```
+#[cfg(feature = "serde")]
+pub fn from_bytes<'a, T: serde::Deserialize<'a>>(buf: &'a [u8]) -> Result<T, crate::de::Error> {
+    T::deserialize(crate::de::Deserializer::from_bytes(buf))
+}
+
+/// Deserialize the pairs in a section into a type, using the given options
+#[cfg(feature = "serde")]
+pub fn from_bytes_with_options<'a, T: serde::Deserialize<'a>>(
+    buf: &'a [u8],
+    options: &ExtractOptions,
+) -> Result<T, crate::de::Error> {
+    T::deserialize(crate::de::Deserializer::from_bytes_with_options(
+        buf, options,
+    ))
+}
+
+/// Iterator over the blocks of an embossed section, created by [`blocks`]
+#[derive(Clone, Debug)]
+pub struct Blocks<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    done: bool,
+    options: ExtractOptions,
+    yielded: usize,
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = Result<EmbossBlock<'a>, EmbossError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut pairs = EmbossIter::single_block(&self.buf[self.pos..], &self.options);
+        pairs.yielded = self.yielded;
+        pairs.base = self.pos;
+        if self.pos > 0 {
+            // Only trailing padding, or bytes that aren't another block, are left over
+            pairs.skip_padding();
+            if !pairs.at_magic() {
+                self.done = true;
+                return pairs.check_trailing().err().map(Err);
+            }
+        }
+
+        // Walk the raw entries, as byte values needn't be valid UTF-8
+        let start = pairs.pos;
+        let mut count = 0;
+        while let Some(entry) = pairs.next_entry() {
+            if let Err(err) = entry {
+                self.done = true;
+                return Some(Err(err));
+            }
+
+            count += 1;
+        }
+
+        let offset = self.pos + start;
+        self.pos += pairs.pos;
+        self.yielded = pairs.yielded;
+        Some(Ok(EmbossBlock {
+            bytes: &pairs.buf[start..pairs.pos],
+            offset,
+            count,
+            options: self.options,
+        }))
+    }
+}
+
+impl FusedIterator for Blocks<'_> {}
+
+/// A single embossed block within a section
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EmbossBlock<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    count: usize,
+    options: ExtractOptions,
+}
+
+/// The length of a v2 header with the given flags, including any checksum and signature
+fn versioned_header_len(flags: u8) -> usize {
+    let mut len = 11;
+    if flags & FLAG_CHECKSUM != 0 {
+        len += 4;
+    }
+
+    if flags & FLAG_SIGNATURE != 0 {
+        len += SIGNATURE_LEN;
+    }
+
+    len
+}
+
+impl<'a> EmbossBlock<'a> {
+    /// The raw bytes of the block, including its header
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Where the block starts in the section
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The number of key-value pairs in the block
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Blocks are never empty, as an empty embossing is rejected while parsing
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn iter(&self) -> EmbossIter<'a> {
+        EmbossIter {
+            base: self.offset,
+            ..EmbossIter::single_block(self.bytes, &self.options)
+        }
+    }
+
+    /// The Ed25519 signature of the block, if it was signed when embossed
+    pub fn signature(&self) -> Option<&'a [u8; SIGNATURE_LEN]> {
+        let (flags, header_len) = self.versioned_header()?;
+        if flags & FLAG_SIGNATURE == 0 {
+            return None;
+        }
+
+        self.bytes[header_len - SIGNATURE_LEN..].first_chunk()
+    }
+
+    /// Verify the block's signature against an Ed25519 public key
+    ///
+    /// The signature covers `COUNT` and every entry of the block, so any change to the
+    /// embossed keys or values invalidates it.
+    #[cfg(feature = "signatures")]
+    pub fn verify(&self, public_key: &[u8; 32]) -> Result<(), EmbossError> {
+        self.verify_signature(public_key)
+            .map_err(|err| err.at(self.offset))
+    }
+
+    #[cfg(feature = "signatures")]
+    fn verify_signature(&self, public_key: &[u8; 32]) -> Result<(), EmbossError> {
+        use ed25519_dalek::{Signature, VerifyingKey};
+
+        let signature = self.signature().ok_or(ErrorKind::MissingSignature)?;
+        let (_, header_len) = self.versioned_header().ok_or(ErrorKind::MissingSignature)?;
+
+        let mut message = self.bytes[7..11].to_vec();
+        message.extend_from_slice(&self.bytes[header_len..]);
+
+        let public_key =
+            VerifyingKey::from_bytes(public_key).map_err(|_| ErrorKind::InvalidSignature)?;
+        public_key
+            .verify_strict(&message, &Signature::from_bytes(signature))
+            .map_err(|_| ErrorKind::InvalidSignature.into())
+    }
+
+    /// The flags and header length of a v2 block, or `None` for a v1 block
+    fn versioned_header(&self) -> Option<(u8, usize)> {
+        if self.bytes.get(4) != Some(&VERSIONED_HEADER_MARKER) {
+            return None;
+        }
+
+        // The header was already validated while parsing the block
+        let flags = self.bytes[6];
+        Some((flags, versioned_header_len(flags)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn extract_basic() {
+        let data = b"\x55\xB0\x77\x1A\x01key\0value\0";
+        let metadata = extract_metadata_into_hashmap(data).unwrap();
+        let value = metadata.get("key").unwrap();
+        assert_eq!(*value, "value")
+    }
+
+    /// Iterate over every pair in a section, stopping at the first error like the collecting
+    /// helpers do, but without needing `alloc`
+    fn walk(data: &[u8]) -> Result<(), EmbossError> {
+        iter(data).try_for_each(|pair| pair.map(drop))
+    }
+
+    #[test]
+    fn iter_is_lazy() {
+        let data = b"\x55\xB0\x77\x1A\x02key\0value\0bad";
+        let mut pairs = iter(data);
+        assert_eq!(pairs.next(), Some(Ok(("key", "value"))));
+        assert_eq!(
+            pairs
+                .next()
+                .map(|pair| pair.map_err(EmbossError::into_kind)),
+            Some(Err(ErrorKind::InvalidEmbossedCString))
+        );
+        assert_eq!(pairs.next(), None);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_multiple_blocks() {
+        let data = b"\x55\xB0\x77\x1A\x01foo\0one\0\x55\xB0\x77\x1A\x02bar\0two\0baz\0three\0";
+        let metadata = extract_metadata_into_vec(data).unwrap();
+        assert_eq!(
+            metadata,
+            vec![("foo", "one"), ("bar", "two"), ("baz", "three")]
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_owned_keeps_duplicates() {
+        let data = b"\x55\xB0\x77\x1A\x01foo\0one\0\0\x55\xB0\x77\x1A\x02foo\0two\0bar\0three\0";
+        let metadata = extract_metadata_into_owned(data).unwrap();
+        assert_eq!(metadata.get("foo"), Some("one"));
+        assert_eq!(
+            metadata.get_all("foo").collect::<Vec<_>>(),
+            vec!["one", "two"]
+        );
+        assert_eq!(metadata.len(), 3);
+    }
+
+    #[cfg(feature = "alloc")]
+    const DUPLICATED: &[u8] =
+        b"\x55\xB0\x77\x1A\x02foo\0one\0bar\0two\0\0\x55\xB0\x77\x1A\x01foo\0three\0";
+
+    #[cfg(feature = "alloc")]
+    fn with_duplicates(duplicates: DuplicatePolicy) -> ExtractOptions {
+        ExtractOptions {
+            duplicates,
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn extract_duplicates_collect_all() {
+        let options = with_duplicates(DuplicatePolicy::CollectAll);
+        assert_eq!(
+            extract_metadata_into_vec_with_options(DUPLICATED, &options),
+            Ok(vec![("foo", "one"), ("bar", "two"), ("foo", "three")])
+        );
+
+        let metadata = extract_metadata_into_owned_with_options(DUPLICATED, &options).unwrap();
+        assert_eq!(
+            metadata.get_all("foo").collect::<Vec<_>>(),
+            vec!["one", "three"]
+        );
+
+        // The default, like a HashMap always has, keeps the last value
+        let metadata = extract_metadata_into_hashmap(DUPLICATED).unwrap();
+        assert_eq!(metadata["foo"], "three");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn extract_duplicates_first_wins() {
+        let options = with_duplicates(DuplicatePolicy::FirstWins);
+        assert_eq!(
+            extract_metadata_into_vec_with_options(DUPLICATED, &options),
+            Ok(vec![("foo", "one"), ("bar", "two")])
+        );
+
+        let metadata = extract_metadata_into_hashmap_with_options(DUPLICATED, &options).unwrap();
+        assert_eq!(metadata["foo"], "one");
+
+        let metadata = extract_typed_metadata_into_hashmap_with_options(DUPLICATED, &options);
+        assert_eq!(metadata.unwrap()["foo"], EmbossValue::Str("one"));
+
+        let metadata = extract_metadata_into_owned_with_options(DUPLICATED, &options).unwrap();
+        assert_eq!(metadata.len(), 2);
+        assert_eq!(metadata.get("foo"), Some("one"));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_duplicates_last_wins() {
+        let options = with_duplicates(DuplicatePolicy::LastWins);
+        assert_eq!(
+            extract_metadata_into_vec_with_options(DUPLICATED, &options),
+            Ok(vec![("bar", "two"), ("foo", "three")])
+        );
+
+        let metadata = extract_metadata_into_owned_with_options(DUPLICATED, &options).unwrap();
+        assert_eq!(metadata.get_all("foo").collect::<Vec<_>>(), vec!["three"]);
+
+        let extraction = extract_metadata_lossy_with_options(DUPLICATED, &options);
+        assert_eq!(
+            lossy_pairs(&extraction),
+            vec![("bar", "two"), ("foo", "three")]
+        );
+
+        // Spans are kept per block, like the blocks themselves
+        let data = b"\x55\xB0\x77\x1A\x02foo\0one\0foo\0two\0";
+        let blocks = extract_with_spans_with_options(data, &options).unwrap();
+        assert_eq!(blocks[0].pairs.len(), 1);
+        assert_eq!(blocks[0].pairs[0].value, "two");
+        assert_eq!(blocks[0].pairs[0].value_span, 17..20);
+        assert_eq!(
+            extract_with_spans_with_options(DUPLICATED, &options).unwrap()[0]
+                .pairs
+                .len(),
+            2
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    fn lossy_pairs<'a>(extraction: &'a LossyExtraction<'_>) -> Vec<(&'a str, &'a str)> {
+        extraction
+            .pairs
+            .iter()
+            .map(|(key, value)| (key.as_ref(), value.as_ref()))
+            .collect()
+    }
+
+    #[cfg(feature = "alloc")]
+    fn lossy_diagnostics(extraction: &LossyExtraction<'_>) -> Vec<(usize, ErrorKind)> {
+        extraction
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.offset, diagnostic.error.kind().clone()))
+            .collect()
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_lossy_clean() {
+        let extraction = extract_metadata_lossy(DUPLICATED);
+        assert_eq!(
+            lossy_pairs(&extraction),
+            extract_metadata_into_vec(DUPLICATED).unwrap()
+        );
+        assert!(extraction.diagnostics.is_empty());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_lossy_invalid_utf8() {
+        let data = b"\x55\xB0\x77\x1A\x02f\xFFo\0one\0bar\0t\xC3o\0";
+        let extraction = extract_metadata_lossy(data);
+        assert_eq!(
+            lossy_pairs(&extraction),
+            vec![("f\u{FFFD}o", "one"), ("bar", "t\u{FFFD}o")]
+        );
+
+        let offsets = extraction
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.offset)
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, vec![6, 18]);
+        assert!(matches!(
+            extraction.diagnostics[0].error.kind(),
+            ErrorKind::InvalidUtf8(_)
+        ));
+        assert!(
+            extraction.diagnostics[1]
+                .to_string()
+                .starts_with("At offset 0x12, in the value of key 'bar': ")
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_lossy_missing_terminator() {
+        let data = b"\x55\xB0\x77\x1A\x02foo\0one\0bar\0tw";
+        let extraction = extract_metadata_lossy(data);
+        assert_eq!(
+            lossy_pairs(&extraction),
+            vec![("foo", "one"), ("bar", "tw")]
+        );
+        assert_eq!(
+            lossy_diagnostics(&extraction),
+            vec![(17, ErrorKind::InvalidEmbossedCString)]
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_custom_magic() {
+        let ours = b"\xA7\x10\xC7\x5E\x01foo\0one\0";
+        let theirs = b"\x55\xB0\x77\x1A\x01bar\0two\0";
+        let options = ExtractOptions {
+            magic: Magic::Exactly(0x5EC7_10A7),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            extract_metadata_into_vec_with_options(ours, &options).unwrap(),
+            vec![("foo", "one")]
+        );
+        assert_eq!(
+            extract_metadata_into_vec(ours).map_err(EmbossError::into_kind),
+            Err(ErrorKind::IncorrectLeadingMagic)
+        );
+        assert_eq!(
+            extract_metadata_into_vec_with_options(theirs, &options)
+                .map_err(EmbossError::into_kind),
+            Err(ErrorKind::IncorrectLeadingMagic)
+        );
+
+        // Several magic numbers can be accepted at once, in any order
+        let data = [&ours[..], &theirs[..]].concat();
+        let options = ExtractOptions {
+            magic: Magic::AnyOf(&[LEADING_MAGIC_BYTES, 0x5EC7_10A7]),
+            ..Default::default()
+        };
+        assert_eq!(
+            extract_metadata_into_vec_with_options(&data, &options).unwrap(),
+            vec![("foo", "one"), ("bar", "two")]
+        );
+        assert_eq!(blocks_with_options(&data, &options).count(), 2);
+
+        // Scanning for blocks passes over anyone else's
+        let mut data = b"garbage".to_vec();
+        data.extend_from_slice(theirs);
+        data.extend_from_slice(ours);
+        let options = ExtractOptions {
+            magic: Magic::Exactly(0x5EC7_10A7),
+            ..Default::default()
+        };
+        let extraction = extract_metadata_lossy_with_options(&data, &options);
+        assert_eq!(lossy_pairs(&extraction), vec![("foo", "one")]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_lossy_skips_to_next_block() {
+        // A v2 block whose only key claims to run far past the end of the buffer
+        let mut data = b"\x55\xB0\x77\x1A\x00\x02\x00\x01\0\0\0\x00\xFF\0\0\0key".to_vec();
+        data.extend_from_slice(b"garbage\x55\xB0\x77\x1A\x01foo\0one\0");
+        data.extend_from_slice(b"junk\x55\xB0\x77\x1A\x01bar\0two\0");
+
+        let extraction = extract_metadata_lossy(&data);
+        assert_eq!(
+            lossy_pairs(&extraction),
+            vec![("foo", "one"), ("bar", "two")]
+        );
+        assert_eq!(
+            lossy_diagnostics(&extraction),
+            vec![(16, ErrorKind::Truncated), (39, ErrorKind::TrailingBytes),]
+        );
+
+        // Nothing is recovered from a buffer without any blocks
+        let extraction = extract_metadata_lossy(b"garbage");
+        assert!(extraction.pairs.is_empty());
+        assert_eq!(
+            lossy_diagnostics(&extraction),
+            vec![(0, ErrorKind::IncorrectLeadingMagic)]
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_lossy_offsets_match_strict() {
+        // Damage in a second block is first reported at the same offset either way
+        let first = b"\x55\xB0\x77\x1A\x01foo\0one\0";
+        let damaged: [&[u8]; 3] = [
+            b"\x55\xB0\x77\x1A\x00\x02\x00\x01\0\0\0\x00\xFF\0\0\0key",
+            b"\x55\xB0\x77\x1A\x00\x02\x00\x01\0\0\0\x00\x03\0\0\0key",
+            b"\x55\xB0\x77\x1A\x00\x02\x00\x01\0\0\0\x0F",
+        ];
+
+        for second in damaged {
+            let data = [&first[..], second].concat();
+            let err = iter(&data).find_map(Result::err).unwrap();
+            let extraction = extract_metadata_lossy(&data);
+            assert_eq!(lossy_pairs(&extraction), vec![("foo", "one")]);
+            assert_eq!(
+                lossy_diagnostics(&extraction)[0],
+                (err.offset().unwrap(), err.kind().clone())
+            );
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_spans_multiple_blocks() {
+        let blocks = extract_with_spans(DUPLICATED).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].header.magic, 0..4);
+        assert_eq!(blocks[1].header.magic, 22..26);
+        assert_eq!(blocks[1].header.count, 26..27);
+
+        let spans = blocks
+            .iter()
+            .flat_map(|block| &block.pairs)
+            .map(|pair| {
+                assert_eq!(&DUPLICATED[pair.key_span.clone()], pair.key.as_bytes());
+                assert_eq!(&DUPLICATED[pair.value_span.clone()], pair.value.as_bytes());
+                (pair.key_span.clone(), pair.value_span.clone())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            vec![(5..8, 9..12), (13..16, 17..20), (27..30, 31..36)]
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_spans_fail() {
+        let data = b"\x55\xB0\x77\x1A\x02foo\0one\0";
+        assert_eq!(
+            extract_with_spans(data).map_err(EmbossError::into_kind),
+            Err(ErrorKind::CountMismatch {
+                expected: 2,
+                found: 1
+            })
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn extract_duplicates_error() {
+        let options = with_duplicates(DuplicatePolicy::Error);
+        let err = ErrorKind::DuplicateKey("foo".to_string());
+        assert_eq!(
+            extract_metadata_into_vec_with_options(DUPLICATED, &options)
+                .map_err(EmbossError::into_kind),
+            Err(err.clone())
+        );
+        assert!(extract_metadata_into_hashmap_with_options(DUPLICATED, &options).is_err());
+        assert!(extract_metadata_into_owned_with_options(DUPLICATED, &options).is_err());
+
+        // Each block is checked on its own when they're kept apart
+        assert_eq!(
+            extract_metadata_into_blocks_with_options(DUPLICATED, &options),
+            Ok(vec![
+                vec![("foo", "one"), ("bar", "two")],
+                vec![("foo", "three")]
+            ])
+        );
+        assert_eq!(
+            extract_with_spans_with_options(DUPLICATED, &options).map(|blocks| blocks.len()),
+            Ok(2)
+        );
+        let data = b"\x55\xB0\x77\x1A\x02foo\0one\0foo\0two\0";
+        assert_eq!(
+            extract_with_spans_with_options(data, &options).map_err(EmbossError::into_kind),
+            Err(err.clone())
+        );
+
+        // Recovering notes every repeat, but keeps the pairs
+        let extraction = extract_metadata_lossy_with_options(DUPLICATED, &options);
+        assert_eq!(extraction.pairs.len(), 3);
+        assert_eq!(lossy_diagnostics(&extraction), vec![(27, err)]);
+
+        // The iterators yield every pair regardless
+        assert_eq!(iter_with_options(DUPLICATED, &options).count(), 3);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_blocks_skips_padding() {
+        let data = b"\x55\xB0\x77\x1A\x01foo\0one\0\0\0\0\x55\xB0\x77\x1A\x01bar\0two\0\0\0";
+        let blocks = blocks(data).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].as_bytes(), b"\x55\xB0\x77\x1A\x01foo\0one\0");
+        assert_eq!(blocks[1].as_bytes(), b"\x55\xB0\x77\x1A\x01bar\0two\0");
+        assert_eq!(
+            blocks[1].iter().collect::<Result<Vec<_>, _>>(),
+            Ok(vec![("bar", "two")])
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_count_ends_block() {
+        // Without honoring the count, the second block's header would be read as a key
+        let data = b"\x55\xB0\x77\x1A\x01foo\0one\0\x55\xB0\x77\x1A\x01bar\0two\0";
+        let blocks = extract_metadata_into_blocks(data).unwrap();
+        assert_eq!(blocks, vec![vec![("foo", "one")], vec![("bar", "two")]]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_fail_in_second_block() {
+        let data = b"\x55\xB0\x77\x1A\x01foo\0one\0\x55\xB0\x77\x1A\x00";
+        if let Err(error) = extract_metadata_into_blocks(data) {
+            return assert_eq!(*error.kind(), ErrorKind::EmptyEmbossing);
+        }
+
+        panic!("expected an error to be returned")
+    }
+
+    #[test]
+    fn extract_fail_count_mismatch() {
+        let data = b"\x55\xB0\x77\x1A\x03foo\0one\0bar\0two\0";
+        if let Err(error) = walk(data) {
+            return assert_eq!(
+                *error.kind(),
+                ErrorKind::CountMismatch {
+                    expected: 3,
+                    found: 2
+                }
+            );
+        }
+
+        panic!("expected an error to be returned")
+    }
+
+    #[test]
+    fn extract_fail_dangling_key() {
+        let data = b"\x55\xB0\x77\x1A\x02foo\0one\0bar\0";
+        if let Err(error) = walk(data) {
+            return assert_eq!(*error.kind(), ErrorKind::DanglingKey);
+        }
+
+        panic!("expected an error to be returned")
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_lenient_ignores_trailing_bytes() {
+        let data = b"\x55\xB0\x77\x1A\x01foo\0one\0bar\0two\0";
+        let metadata = extract_metadata_into_vec(data).unwrap();
+        assert_eq!(metadata, vec![("foo", "one")]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_strict_allows_padding() {
+        let options = ExtractOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let data = b"\x55\xB0\x77\x1A\x01foo\0one\0\0\0\0";
+        let metadata = extract_metadata_into_vec_with_options(data, &options).unwrap();
+        assert_eq!(metadata, vec![("foo", "one")]);
+
+        let blocks = extract_metadata_into_blocks_with_options(data, &options).unwrap();
+        assert_eq!(blocks, vec![vec![("foo", "one")]]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_strict_fail_trailing_bytes() {
+        let options = ExtractOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let data = b"\x55\xB0\x77\x1A\x01foo\0one\0\0bar\0two\0";
+        if let Err(error) = extract_metadata_into_vec_with_options(data, &options) {
+            assert_eq!(*error.kind(), ErrorKind::TrailingBytes);
+        } else {
+            panic!("expected an error to be returned")
+        }
+
+        if let Err(error) = extract_metadata_into_blocks_with_options(data, &options) {
+            return assert_eq!(*error.kind(), ErrorKind::TrailingBytes);
+        }
+
+        panic!("expected an error to be returned")
+    }
+
+    #[test]
+    fn extract_fail_magic_without_count() {
+        let data = b"\x55\xB0\x77\x1A";
+        if let Err(error) = walk(data) {
+            return assert_eq!(*error.kind(), ErrorKind::Truncated);
+        }
+
+        panic!("expected an error to be returned")
+    }
+
+    #[test]
+    fn extract_fail_magic_without_count_in_second_block() {
+        let data = b"\x55\xB0\x77\x1A\x01foo\0one\0\x55\xB0\x77\x1A";
+        let error = walk(data).unwrap_err();
+        assert_eq!(*error.kind(), ErrorKind::Truncated);
+        assert_eq!(error.offset(), Some(13));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_fail_too_many_pairs() {
+        let options = ExtractOptions {
+            max_pairs: Some(2),
+            ..Default::default()
+        };
+
+        let data = b"\x55\xB0\x77\x1A\x02foo\0one\0bar\0two\0";
+        let metadata = extract_metadata_into_vec_with_options(data, &options).unwrap();
+        assert_eq!(metadata.len(), 2);
+
+        let data = b"\x55\xB0\x77\x1A\x02foo\0one\0bar\0two\0\x55\xB0\x77\x1A\x01baz\0three\0";
+        if let Err(error) = extract_metadata_into_blocks_with_options(data, &options) {
+            return assert_eq!(*error.kind(), ErrorKind::TooManyPairs { limit: 2 });
+        }
+
+        panic!("expected an error to be returned")
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_fail_string_too_long() {
+        let options = ExtractOptions {
+            max_string_len: Some(3),
+            ..Default::default()
+        };
+
+        let data = b"\x55\xB0\x77\x1A\x01foo\0one\0";
+        let metadata = extract_metadata_into_vec_with_options(data, &options).unwrap();
+        assert_eq!(metadata, vec![("foo", "one")]);
+
+        let data = b"\x55\xB0\x77\x1A\x01foo\0three\0";
+        if let Err(error) = extract_metadata_into_vec_with_options(data, &options) {
+            return assert_eq!(*error.kind(), ErrorKind::StringTooLong { limit: 3 });
+        }
+
+        panic!("expected an error to be returned")
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_v2() {
+        let data = b"\x55\xB0\x77\x1A\x00\x02\x00\x02\0\0\0\x00\x03\0\0\0key\x05\0\0\0value\x00\x03\0\0\0nul\x03\0\0\0a\0b";
+        let metadata = extract_metadata_into_vec(data).unwrap();
+        assert_eq!(metadata, vec![("key", "value"), ("nul", "a\0b")]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_mixed_versions() {
+        let data = b"\x55\xB0\x77\x1A\x01foo\0one\0\x55\xB0\x77\x1A\x00\x02\x00\x01\0\0\0\x00\x03\0\0\0bar\x03\0\0\0two";
+        let blocks = extract_metadata_into_blocks(data).unwrap();
+        assert_eq!(blocks, vec![vec![("foo", "one")], vec![("bar", "two")]]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn extract_typed() {
+        let data = b"\x55\xB0\x77\x1A\x00\x02\x00\x05\0\0\0\
+            \x00\x03\0\0\0str\x03\0\0\0abc\
+            \x01\x03\0\0\0u64\x02\0\0\x0042\
+            \x02\x03\0\0\0i64\x02\0\0\0-7\
+            \x03\x04\0\0\0bool\x05\0\0\0false\
+            \x04\x02\0\0\0ts\x0A\0\0\x001620000000";
+
+        let metadata = extract_typed_metadata_into_vec(data).unwrap();
+        assert_eq!(
+            metadata,
+            vec![
+                ("str", EmbossValue::Str("abc")),
+                ("u64", EmbossValue::U64(42)),
+                ("i64", EmbossValue::I64(-7)),
+                ("bool", EmbossValue::Bool(false)),
+                ("ts", EmbossValue::Timestamp(1620000000)),
+            ]
+        );
+
+        // The text of typed values is still available through the string API
+        let metadata = extract_metadata_into_hashmap(data).unwrap();
+        assert_eq!(metadata.get("u64"), Some(&"42"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn extract_typed_v1_is_str() {
+        let data = b"\x55\xB0\x77\x1A\x01key\x0042\0";
+        let metadata = extract_typed_metadata_into_hashmap(data).unwrap();
+        assert_eq!(metadata.get("key"), Some(&EmbossValue::Str("42")));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_typed_fail_invalid_value() {
+        let data = b"\x55\xB0\x77\x1A\x00\x02\x00\x01\0\0\0\x01\x03\0\0\0u64\x03\0\0\0abc";
+        if let Err(error) = extract_typed_metadata_into_vec(data) {
+            return assert_eq!(*error.kind(), ErrorKind::InvalidTypedValue(ValueKind::U64));
+        }
+
+        panic!("expected an error to be returned")
+    }
+
+    #[test]
+    fn extract_fail_unknown_value_kind() {
+        let data = b"\x55\xB0\x77\x1A\x00\x02\x00\x01\0\0\0\xEE\x03\0\0\0key\x03\0\0\0abc";
+        if let Err(error) = walk(data) {
+            return assert_eq!(*error.kind(), ErrorKind::UnknownValueKind(0xEE));
+        }
+
+        panic!("expected an error to be returned")
+    }
+
+    emboss_macros::emboss_many!(
+        items = [
+            { key = "name", value = "emboss" },
+            { key = "build", value = 42, variant_name = "Build" },
+            { key = "offset", value = -3i32 },
+            { key = "debug", value = true },
+            { key = "built-at", value = Timestamp(1620000000) },
+        ],
+        stored_in = "__emboss_typed",
+        export_name = "typed_test"
+    );
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn embossed_typed_values() {
+        let embossed = &typed_test::EMBOSSED;
+        assert_eq!(embossed.get_str("name"), Some("emboss"));
+        assert_eq!(embossed.get_u64("build"), Some(42));
+        assert_eq!(embossed.get_i64("offset"), Some(-3));
+        assert_eq!(embossed.get_bool("debug"), Some(true));
+        assert_eq!(embossed.get_timestamp("built-at"), Some(1620000000));
+        assert_eq!(embossed.get_u64("name"), None);
+        assert_eq!(embossed.get_by_key("build"), Some(("build", "42")));
+        assert_eq!(
+            embossed.get_typed_by_kind(typed_test::EmbossedKeyKind::Build),
+            ("build", EmbossValue::U64(42))
+        );
+
+        let bytes = unsafe {
+            core::slice::from_raw_parts(
+                (embossed as *const typed_test::Embossed).cast::<u8>(),
+                core::mem::size_of::<typed_test::Embossed>(),
+            )
+        };
+        let metadata = extract_typed_metadata_into_vec(bytes).unwrap();
+        assert_eq!(
+            metadata,
+            vec![
+                ("name", EmbossValue::Str("emboss")),
+                ("build", EmbossValue::U64(42)),
+                ("offset", EmbossValue::I64(-3)),
+                ("debug", EmbossValue::Bool(true)),
+                ("built-at", EmbossValue::Timestamp(1620000000)),
+            ]
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_typed_bytes() {
+        let data =
+            b"\x55\xB0\x77\x1A\x00\x02\x00\x01\0\0\0\x05\x04\0\0\0blob\x03\0\0\0\xFF\x00\x01";
+        let metadata = extract_typed_metadata_into_vec(data).unwrap();
+        assert_eq!(
+            metadata,
+            vec![("blob", EmbossValue::Bytes(b"\xFF\x00\x01"))]
+        );
+        assert_eq!(metadata[0].1.to_string(), "ff0001");
+
+        let block = blocks(data).next().unwrap().unwrap();
+        assert_eq!(block.as_bytes(), data);
+
+        // Bytes that aren't UTF-8 can't be read through the string API
+        if let Err(error) = extract_metadata_into_vec(data) {
+            return assert!(matches!(error.kind(), ErrorKind::InvalidUtf8(_)));
+        }
+
+        panic!("expected an error to be returned")
+    }
+
+    emboss_macros::emboss_many!(
+        items = [
+            { key = "blob", value = b"\x00\x01\x7F", variant_name = "Blob" },
+            { key = "key", value = b"\xDE\xAD\xBE\xEF", variant_name = "Key" },
+            { key = "text", value = b"utf-8", variant_name = "Text" },
+            { key = "name", value = "bytes", variant_name = "Name" },
+        ],
+        stored_in = "__emboss_bytes",
+        export_name = "bytes_test"
+    );
+
+    #[test]
+    fn embossed_bytes() {
+        let embossed = &bytes_test::EMBOSSED;
+        assert_eq!(embossed.get_bytes("blob"), Some(&b"\x00\x01\x7F"[..]));
+        assert_eq!(embossed.get_bytes("key"), Some(&b"\xDE\xAD\xBE\xEF"[..]));
+        assert_eq!(
+            embossed.get_typed_by_kind(bytes_test::EmbossedKeyKind::Key),
+            ("key", EmbossValue::Bytes(b"\xDE\xAD\xBE\xEF"))
+        );
+        assert_eq!(embossed.get_str("text"), None);
+
+        // Only values that aren't bytes are handed out as strings
+        assert_eq!(embossed.get_by_key("blob"), None);
+        assert_eq!(embossed.get_by_key("key"), None);
+        assert_eq!(embossed.get_by_key("text"), None);
+        assert_eq!(embossed.get_by_index(1), None);
+        assert_eq!(embossed.get_by_index(3), Some(("name", "bytes")));
+        assert_eq!(embossed.get_by_kind(bytes_test::EmbossedKeyKind::Key), None);
+        assert_eq!(
+            embossed.get_by_kind(bytes_test::EmbossedKeyKind::Name),
+            Some(("name", "bytes"))
+        );
+    }
+
+    #[test]
+    fn extract_v2_fail_unsupported_version() {
+        let data = b"\x55\xB0\x77\x1A\x00\x09\x01\0\0\0";
+        if let Err(error) = walk(data) {
+            return assert_eq!(*error.kind(), ErrorKind::UnsupportedVersion(9));
+        }
+
+        panic!("expected an error to be returned")
+    }
+
+    #[test]
+    fn extract_v2_fail_truncated() {
+        let data = b"\x55\xB0\x77\x1A\x00\x02\x00\x01\0";
+        if let Err(error) = walk(data) {
+            assert_eq!(*error.kind(), ErrorKind::Truncated);
+        } else {
+            panic!("expected an error to be returned")
+        }
+
+        let data = b"\x55\xB0\x77\x1A\x00\x02\x00\x01\0\0\0\x00\x03\0\0\0key\x05\0\0\0val";
+        if let Err(error) = walk(data) {
+            return assert_eq!(*error.kind(), ErrorKind::Truncated);
+        }
+
+        panic!("expected an error to be returned")
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_v2_fail_string_too_long() {
+        let options = ExtractOptions {
+            max_string_len: Some(2),
+            ..Default::default()
+        };
+
+        let data = b"\x55\xB0\x77\x1A\x00\x02\x00\x01\0\0\0\x00\xFF\xFF\xFF\xFFkey";
+        if let Err(error) = extract_metadata_into_vec_with_options(data, &options) {
+            return assert_eq!(*error.kind(), ErrorKind::StringTooLong { limit: 2 });
+        }
+
+        panic!("expected an error to be returned")
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_big_endian_v1() {
+        // Built for a big-endian target before the magic was written in a fixed byte order
+        let data = b"\x1A\x77\xB0\x55\x01key\0value\0";
+        let metadata = extract_metadata_into_vec(data).unwrap();
+        assert_eq!(metadata, vec![("key", "value")]);
+    }
+
+    #[test]
+    fn custom_magic_is_little_endian() {
+        let magic = Magic::Exactly(0x5EC7_10A7);
+        assert!(magic.matches(&0x5EC7_10A7u32.to_le_bytes()));
+        assert!(!magic.matches(&0x5EC7_10A7u32.to_be_bytes()));
+        assert!(!Magic::AnyOf(&[0x5EC7_10A7]).matches(&0x5EC7_10A7u32.to_be_bytes()));
+
+        // Only the default magic was ever written big-endian
+        assert!(Magic::Default.matches(&LEADING_MAGIC_BYTES.to_be_bytes()));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_big_endian_v2() {
+        let data = b"\x1A\x77\xB0\x55\x00\x02\x00\x01\0\0\0\x00\x03\0\0\0key\x05\0\0\0value";
+        let metadata = extract_metadata_into_vec(data).unwrap();
+        assert_eq!(metadata, vec![("key", "value")]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_mixed_endian_blocks() {
+        let data = b"\x1A\x77\xB0\x55\x01foo\0one\0\0\x55\xB0\x77\x1A\x01bar\0two\0";
+        let blocks = extract_metadata_into_blocks(data).unwrap();
+        assert_eq!(blocks, vec![vec![("foo", "one")], vec![("bar", "two")]]);
+    }
+
+    emboss_macros::emboss!(
+        key = "header",
+        value = "little-endian",
+        stored_in = "__emboss_header",
+        export_name = "header_test"
+    );
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn embossed_header_is_little_endian() {
+        let embossed = unsafe {
+            core::slice::from_raw_parts(
+                (&header_test::EMBOSSED as *const header_test::Embossed).cast::<u8>(),
+                core::mem::size_of::<header_test::Embossed>(),
+            )
+        };
+
+        assert_eq!(embossed[..4], LEADING_MAGIC_BYTES.to_le_bytes());
+        assert_eq!(embossed[4..7], [0x00, 0x02, FLAG_CHECKSUM]);
+        assert_eq!(embossed[7..11], [0x01, 0x00, 0x00, 0x00]);
+
+        let checksum = Crc32::new()
+            .update(&embossed[7..11])
+            .update(&embossed[15..])
+            .finish();
+        assert_eq!(embossed[11..15], checksum.to_le_bytes());
+
+        let metadata = extract_metadata_into_vec(embossed).unwrap();
+        assert_eq!(metadata, vec![("header", "little-endian")]);
+    }
+
+    emboss_macros::emboss!(
+        key = "checksum",
+        value = "off",
+        stored_in = "__emboss_unchecked",
+        export_name = "unchecked_test",
+        checksum = false
+    );
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn embossed_header_without_checksum() {
+        let embossed = unsafe {
+            core::slice::from_raw_parts(
+                (&unchecked_test::EMBOSSED as *const unchecked_test::Embossed).cast::<u8>(),
+                core::mem::size_of::<unchecked_test::Embossed>(),
+            )
+        };
+
+        assert_eq!(embossed[4..11], [0x00, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00]);
+
+        let metadata = extract_metadata_into_vec(embossed).unwrap();
+        assert_eq!(metadata, vec![("checksum", "off")]);
+    }
+
+    #[cfg(feature = "alloc")]
+    fn checksummed_block() -> Vec<u8> {
+        let count = 1u32.to_le_bytes();
+        let entries = b"\x00\x03\0\0\0key\x05\0\0\0value";
+        let checksum = Crc32::new().update(&count).update(entries).finish();
+
+        let mut data = b"\x55\xB0\x77\x1A\x00\x02\x01".to_vec();
+        data.extend_from_slice(&count);
+        data.extend_from_slice(&checksum.to_le_bytes());
+        data.extend_from_slice(entries);
+        data
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_spans_checksummed_block() {
+        let data = checksummed_block();
+        let blocks = extract_with_spans(&data).unwrap();
+        assert_eq!(
+            blocks,
+            vec![SpannedBlock {
+                header: HeaderSpans {
+                    magic: 0..4,
+                    count: 7..11,
+                    checksum: Some(11..15),
+                    signature: None,
+                },
+                pairs: vec![SpannedPair {
+                    key: "key",
+                    value: "value",
+                    key_span: 20..23,
+                    value_span: 27..32,
+                }],
+            }]
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_lossy_checksum_mismatch() {
+        let mut data = checksummed_block();
+        let last = data.len() - 1;
+        data[last] = b'E';
+
+        let extraction = extract_metadata_lossy(&data);
+        assert_eq!(
+            extraction.pairs,
+            vec![(Cow::Borrowed("key"), Cow::Borrowed("valuE"))]
+        );
+        assert_eq!(extraction.diagnostics.len(), 1);
+        assert_eq!(extraction.diagnostics[0].offset, 11);
+        assert!(matches!(
+            extraction.diagnostics[0].error.kind(),
+            ErrorKind::ChecksumMismatch { .. }
+        ));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_checksummed_block() {
+        let data = checksummed_block();
+        let metadata = extract_metadata_into_vec(&data).unwrap();
+        assert_eq!(metadata, vec![("key", "value")]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_fail_checksum_mismatch() {
+        let mut data = checksummed_block();
+        let last = data.len() - 1;
+        data[last] = b'E';
+
+        let expected = u32::from_le_bytes(data[11..15].try_into().unwrap());
+        match extract_metadata_into_vec(&data).map_err(EmbossError::into_kind) {
+            Err(ErrorKind::ChecksumMismatch { expected: e, found }) => {
+                assert_eq!(e, expected);
+                assert_ne!(found, expected);
+            }
+            other => panic!("expected a checksum mismatch, got {:?}", other),
+        }
+
+        // Nothing from the corrupted block is yielded, not even its first key
+        assert_eq!(iter(&data).next().unwrap().unwrap_err().into_kind(), {
+            let found = Crc32::new()
+                .update(&data[7..11])
+                .update(&data[15..])
+                .finish();
+            ErrorKind::ChecksumMismatch { expected, found }
+        });
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_skip_checksums() {
+        let mut data = checksummed_block();
+        let last = data.len() - 1;
+        data[last] = b'E';
+
+        let options = ExtractOptions {
+            skip_checksums: true,
+            ..Default::default()
+        };
+        let metadata = extract_metadata_into_vec_with_options(&data, &options).unwrap();
+        assert_eq!(metadata, vec![("key", "valuE")]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_checksummed_block_truncated() {
+        let data = checksummed_block();
+        let truncated = &data[..data.len() - 2];
+        assert_eq!(
+            extract_metadata_into_vec(truncated)
+                .unwrap_err()
+                .into_kind(),
+            ErrorKind::Truncated
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_signed_block_without_verifying() {
+        let mut data = b"\x55\xB0\x77\x1A\x00\x02\x02\x01\0\0\0".to_vec();
+        data.extend_from_slice(&[0xAB; SIGNATURE_LEN]);
+        data.extend_from_slice(b"\x00\x03\0\0\0key\x05\0\0\0value");
+
+        let mut blocks = blocks(&data);
+        let block = blocks.next().unwrap().unwrap();
+        assert_eq!(block.signature(), Some(&[0xAB; SIGNATURE_LEN]));
+        assert_eq!(
+            block.iter().collect::<Result<Vec<_>, _>>().unwrap(),
+            vec![("key", "value")]
+        );
+        assert!(blocks.next().is_none());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extract_fail_compressed_value() {
+        // A deflated string, which can't be borrowed straight from the section
+        let data = b"\x55\xB0\x77\x1A\x00\x02\x00\x01\0\0\0\x10\x03\0\0\0key\x02\0\0\0\x03\x00";
+        assert_eq!(
+            extract_metadata_into_vec(data).map_err(EmbossError::into_kind),
+            Err(ErrorKind::CompressedValue(Codec::Deflate))
+        );
+        assert_eq!(
+            extract_typed_metadata_into_vec(data).map_err(EmbossError::into_kind),
+            Err(ErrorKind::CompressedValue(Codec::Deflate))
+        );
+
+        // Blocks are still walked, as only the values themselves are affected
+        assert_eq!(blocks(data).next().unwrap().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn extract_fail_unknown_codec() {
+        let data = b"\x55\xB0\x77\x1A\x00\x02\x00\x01\0\0\0\x70\x03\0\0\0key\x05\0\0\0value";
+        assert_eq!(
+            walk(data).map_err(EmbossError::into_kind),
+            Err(ErrorKind::UnknownValueKind(0x70))
+        );
+    }
+
+    #[cfg(feature = "deflate")]
+    mod compression {
+        use miniz_oxide::deflate::compress_to_vec;
+
+        use super::*;
+
+        const LICENSE: &str = "Permission is hereby granted, free of charge, to any person \
+            obtaining a copy of this software, to deal in the software without restriction, \
+            subject to the following conditions: the above copyright notice and this \
+            permission notice shall be included in all copies of the software.";
+
+        fn block(entries: &[(u8, &str, &[u8])]) -> Vec<u8> {
+            let mut data = b"\x55\xB0\x77\x1A\x00\x02\x00".to_vec();
+            data.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+            for (tag, key, value) in entries {
+                data.push(*tag);
+                data.extend_from_slice(&(key.len() as u32).to_le_bytes());
+                data.extend_from_slice(key.as_bytes());
+                data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                data.extend_from_slice(value);
+            }
+
+            data
+        }
+
+        #[test]
+        fn extract_decompressed() {
+            let compressed = compress_to_vec(LICENSE.as_bytes(), 10);
+            let deflated_str = emboss_common::entry_tag(ValueKind::Str, Codec::Deflate);
+            let data = block(&[
+                (deflated_str, "license", &compressed),
+                (ValueKind::Str as u8, "version", b"1.2.3"),
+            ]);
+
+            let metadata = extract_decompressed_metadata_into_vec(&data).unwrap();
+            assert_eq!(
+                metadata,
+                vec![
+                    ("license", Cow::Owned(LICENSE.to_string())),
+                    ("version", Cow::Borrowed("1.2.3")),
+                ]
+            );
+
+            #[cfg(feature = "std")]
+            {
+                let metadata = extract_decompressed_metadata_into_hashmap(&data).unwrap();
+                assert_eq!(metadata["license"], LICENSE);
+            }
+        }
+
+        #[test]
+        fn extract_decompressed_limit() {
+            let compressed = compress_to_vec(LICENSE.as_bytes(), 10);
+            let deflated_str = emboss_common::entry_tag(ValueKind::Str, Codec::Deflate);
+            let data = block(&[(deflated_str, "license", &compressed)]);
+
+            // The limit applies to the decompressed value, not what's stored
+            assert!(compressed.len() < 200 && LICENSE.len() > 200);
+            let options = ExtractOptions {
+                max_string_len: Some(200),
+                ..Default::default()
+            };
+            let mut pairs = iter_decompressed_with_options(&data, &options);
+            assert_eq!(
+                pairs
+                    .next()
+                    .map(|pair| pair.map_err(EmbossError::into_kind)),
+                Some(Err(ErrorKind::StringTooLong { limit: 200 }))
+            );
+            assert_eq!(pairs.next(), None);
+        }
+
+        #[test]
+        fn extract_decompressed_fail_invalid() {
+            let deflated_str = emboss_common::entry_tag(ValueKind::Str, Codec::Deflate);
+            let data = block(&[(deflated_str, "license", b"\xFF\xFF\xFF")]);
+            assert_eq!(
+                extract_decompressed_metadata_into_vec(&data).map_err(EmbossError::into_kind),
+                Err(ErrorKind::InvalidCompressedValue)
+            );
+        }
+    }
+
+    #[cfg(feature = "signatures")]
+    mod signatures {
+        use ed25519_dalek::{Signer, SigningKey};
+
+        use super::*;
+
+        const ENTRIES: &[u8] = b"\x00\x07\0\0\0version\x05\0\0\x001.2.3";
+
+        fn signed_block(key: &SigningKey, entries: &[u8], flags: u8) -> Vec<u8> {
+            let count = 1u32.to_le_bytes();
+            let mut message = count.to_vec();
+            message.extend_from_slice(entries);
+
+            let mut data = b"\x55\xB0\x77\x1A\x00\x02".to_vec();
+            data.push(flags | FLAG_SIGNATURE);
+            data.extend_from_slice(&count);
+            if flags & FLAG_CHECKSUM != 0 {
+                let checksum = Crc32::new().update(&message).finish();
+                data.extend_from_slice(&checksum.to_le_bytes());
+            }
+
+            data.extend_from_slice(&key.sign(&message).to_bytes());
+            data.extend_from_slice(entries);
+            data
+        }
+
+        #[test]
+        fn extract_spans_signed_block() {
+            let key = SigningKey::from_bytes(&[7; 32]);
+
+            let data = signed_block(&key, ENTRIES, 0);
+            let header = &extract_with_spans(&data).unwrap()[0].header;
+            assert_eq!(header.checksum, None);
+            assert_eq!(header.signature, Some(11..75));
+
+            let data = signed_block(&key, ENTRIES, FLAG_CHECKSUM);
+            let blocks = extract_with_spans(&data).unwrap();
+            assert_eq!(blocks[0].header.checksum, Some(11..15));
+            assert_eq!(blocks[0].header.signature, Some(15..79));
+            assert_eq!(blocks[0].pairs[0].value_span, 95..100);
+        }
+
+        #[test]
+        fn verify_signed_block() {
+            let key = SigningKey::from_bytes(&[7; 32]);
+            let public_key = key.verifying_key().to_bytes();
+
+            for flags in [0, FLAG_CHECKSUM] {
+                let data = signed_block(&key, ENTRIES, flags);
+                assert_eq!(verify(&data, &public_key), Ok(()));
+                assert_eq!(
+                    extract_metadata_into_vec(&data).unwrap(),
+                    vec![("version", "1.2.3")]
+                );
+            }
+        }
+
+        #[test]
+        fn verify_multiple_signed_blocks() {
+            let key = SigningKey::from_bytes(&[7; 32]);
+            let public_key = key.verifying_key().to_bytes();
+
+            let mut data = signed_block(&key, ENTRIES, FLAG_CHECKSUM);
+            data.extend_from_slice(&[0; 3]);
+            data.extend_from_slice(&signed_block(&key, ENTRIES, 0));
+            assert_eq!(verify(&data, &public_key), Ok(()));
+
+            // Every block has to be signed, not just the first
+            data.extend_from_slice(b"\x55\xB0\x77\x1A\x01key\0value\0");
+            assert_eq!(
+                verify(&data, &public_key).map_err(EmbossError::into_kind),
+                Err(ErrorKind::MissingSignature)
+            );
+        }
+
+        #[test]
+        fn verify_fail_wrong_key() {
+            let key = SigningKey::from_bytes(&[7; 32]);
+            let other_key = SigningKey::from_bytes(&[8; 32]).verifying_key().to_bytes();
+
+            let data = signed_block(&key, ENTRIES, FLAG_CHECKSUM);
+            assert_eq!(
+                verify(&data, &other_key).map_err(EmbossError::into_kind),
+                Err(ErrorKind::InvalidSignature)
+            );
+        }
+
+        #[test]
+        fn verify_fail_tampered_value() {
+            let key = SigningKey::from_bytes(&[7; 32]);
+            let public_key = key.verifying_key().to_bytes();
+
+            let mut data = signed_block(&key, ENTRIES, 0);
+            let last = data.len() - 1;
+            data[last] = b'4';
+
+            assert_eq!(
+                extract_metadata_into_vec(&data).unwrap(),
+                vec![("version", "1.2.4")]
+            );
+            assert_eq!(
+                verify(&data, &public_key).map_err(EmbossError::into_kind),
+                Err(ErrorKind::InvalidSignature)
+            );
+        }
+
+        #[test]
+        fn verify_fail_unsigned() {
+            let public_key = SigningKey::from_bytes(&[7; 32]).verifying_key().to_bytes();
+
+            let v1 = b"\x55\xB0\x77\x1A\x01key\0value\0";
+            assert_eq!(
+                verify(v1, &public_key).map_err(EmbossError::into_kind),
+                Err(ErrorKind::MissingSignature)
+            );
+
+            let v2 = b"\x55\xB0\x77\x1A\x00\x02\x00\x01\0\0\0\x00\x03\0\0\0key\x05\0\0\0value";
+            assert_eq!(
+                verify(v2, &public_key).map_err(EmbossError::into_kind),
+                Err(ErrorKind::MissingSignature)
+            );
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn extract_fail_incorrect_magic() {
+        let data = b"\x44\xB0\x77\x1A\x01key\0value\0";
+        if let Err(error) = extract_metadata_into_hashmap(data) {
+            return assert_eq!(*error.kind(), ErrorKind::IncorrectLeadingMagic);
+        }
+
+        panic!("expected an error to be returned")
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn extract_fail_context() {
+        use std::error::Error;
+
+        let data = b"\x55\xB0\x77\x1A\x02foo\0one\0bar\0t\xC3o\0";
+        let err = extract_metadata_into_vec(data).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidUtf8(_)));
+        assert_eq!(err.offset(), Some(18));
+        assert_eq!(err.pair(), Some(1));
+        assert_eq!(err.key(), Some("bar"));
+        assert!(
+            err.to_string()
+                .starts_with("At offset 0x12, in the value of key 'bar': invalid utf-8")
+        );
+        assert!(err.source().unwrap().is::<std::str::Utf8Error>());
+
+        // Offsets count from the start of the section, not of the block
+        let mut data = b"\x55\xB0\x77\x1A\x01foo\0one\0\0\0".to_vec();
+        data.extend_from_slice(b"\x55\xB0\x77\x1A\x00\x02\x00\x01\0\0\0\x00\x03\0\0\0key");
+        data.extend_from_slice(b"\x05\0\0\0value");
+        assert_eq!(blocks(&data).nth(1).unwrap().unwrap().offset(), 15);
+
+        data.truncate(data.len() - 2);
+        let err = extract_metadata_into_vec(&data).unwrap_err();
+        assert_eq!(*err.kind(), ErrorKind::Truncated);
+        assert_eq!(err.offset(), Some(38));
+        assert_eq!(err.pair(), Some(1));
+        assert_eq!(err.key(), Some("key"));
+        assert_eq!(
+            std::io::Error::from(err).kind(),
+            std::io::ErrorKind::UnexpectedEof
+        );
+
+        let err = extract_metadata_into_vec(b"garbage").unwrap_err();
+        assert_eq!(err.to_string(), format!("At offset 0x0: {}", err.kind()));
+        assert_eq!(
+            std::io::Error::from(err).kind(),
+            std::io::ErrorKind::InvalidData
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn extract_fail_on_empty_data() {
+        let data = b"\x55\xB0\x77\x1A\x00";
+        if let Err(error) = extract_metadata_into_hashmap(data) {
+            return assert_eq!(*error.kind(), ErrorKind::EmptyEmbossing);
+        }
+
+        panic!("expected an error to be returned")
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn extract_fail_invalid_utf8() {
+        let data = &[0x55, 0xB0, 0x77, 0x1A, 0x01, 0xB0, 0x55, 0x0, 0x49, 0x0];
+        if let Err(error) = extract_metadata_into_hashmap(data) {
+            return assert!(matches!(error.kind(), ErrorKind::InvalidUtf8(_)));
+        }
+
+        panic!("expected an error to be returned")
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn extract_fail_invalid_c_string() {
+        let data = b"\x55\xB0\x77\x1A\x01foo\0bar";
+        if let Err(error) = extract_metadata_into_hashmap(data) {
+            return assert_eq!(*error.kind(), ErrorKind::InvalidEmbossedCString);
+        }
+
+        panic!("expected an error to be returned")
+    }
+
+    #[cfg(feature = "alloc")]
+    mod properties {
+        use proptest::{collection::vec, prelude::*};
+
+        use super::*;
+
+        fn encode(pairs: &[(String, String)]) -> Vec<u8> {
+            let mut buf = LEADING_MAGIC_BYTES.to_le_bytes().to_vec();
+            buf.push(pairs.len() as u8);
+            for (key, value) in pairs {
+                buf.extend_from_slice(key.as_bytes());
+                buf.push(0);
+                buf.extend_from_slice(value.as_bytes());
+                buf.push(0);
+            }
+
+            buf
+        }
+
+        fn encode_v2(pairs: &[(String, String)]) -> Vec<u8> {
+            let mut buf = LEADING_MAGIC_BYTES.to_le_bytes().to_vec();
+            buf.extend_from_slice(&[VERSIONED_HEADER_MARKER, FORMAT_VERSION, 0]);
+            buf.extend_from_slice(&(pairs.len() as u32).to_le_bytes());
+            for (key, value) in pairs {
+                buf.push(ValueKind::Str as u8);
+                buf.extend_from_slice(&(key.len() as u32).to_le_bytes());
+                buf.extend_from_slice(key.as_bytes());
+                buf.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                buf.extend_from_slice(value.as_bytes());
+            }
+
+            buf
+        }
+
+        fn arb_pairs() -> impl Strategy<Value = Vec<(String, String)>> {
+            vec(("[^\\x00]{0,16}", "[^\\x00]{0,16}"), 1..16)
+        }
+
+        proptest! {
+            #[test]
+            fn never_panics_on_arbitrary_bytes(data in vec(any::<u8>(), 0..256)) {
+                let limited = ExtractOptions {
+                    strict: true,
+                    max_pairs: Some(4),
+                    max_string_len: Some(8),
+                    skip_checksums: true,
+                    duplicates: DuplicatePolicy::Error,
+                    magic: Magic::AnyOf(&[LEADING_MAGIC_BYTES, 0x0102_0304]),
+                };
+
+                for options in [ExtractOptions::default(), limited] {
+                    let _ = extract_metadata_into_vec_with_options(&data, &options);
+                    let _ = extract_metadata_into_blocks_with_options(&data, &options);
+                    let _ = extract_metadata_lossy_with_options(&data, &options);
+                }
+            }
+
+            #[test]
+            fn never_panics_after_valid_header(data in vec(any::<u8>(), 0..256)) {
+                let mut buf = LEADING_MAGIC_BYTES.to_le_bytes().to_vec();
+                buf.extend_from_slice(&data);
+                let _ = extract_metadata_into_vec(&buf);
+                let _ = extract_metadata_into_blocks(&buf);
+                let _ = extract_metadata_lossy(&buf);
+            }
+
+            #[test]
+            fn never_panics_on_mutated_block(
+                pairs in arb_pairs(),
+                index in any::<prop::sample::Index>(),
+                byte in any::<u8>(),
+            ) {
+                let mut buf = encode(&pairs);
+                let index = index.index(buf.len());
+                buf[index] = byte;
+                let _ = extract_metadata_into_vec(&buf);
+                let _ = extract_metadata_into_blocks(&buf);
+                let _ = extract_metadata_lossy(&buf);
+            }
+
+            #[test]
+            fn never_panics_on_truncated_block(
+                pairs in arb_pairs(),
+                len in any::<prop::sample::Index>(),
+            ) {
+                let buf = encode(&pairs);
+                let buf = &buf[..len.index(buf.len())];
+                prop_assert!(extract_metadata_into_vec(buf).is_err());
+            }
+
+            #[test]
+            fn never_panics_on_mutated_v2_block(
+                pairs in arb_pairs(),
+                index in any::<prop::sample::Index>(),
+                byte in any::<u8>(),
+            ) {
+                let mut buf = encode_v2(&pairs);
+                let index = index.index(buf.len());
+                buf[index] = byte;
+                let _ = extract_metadata_into_vec(&buf);
+                let _ = extract_metadata_into_blocks(&buf);
+            }
+
+            #[test]
+            fn round_trips_v2(pairs in vec((".{0,16}", ".{0,16}"), 1..16)) {
+                let buf = encode_v2(&pairs);
+                let metadata = extract_metadata_into_vec(&buf).unwrap();
+                let expected = pairs
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), value.as_str()))
+                    .collect::<Vec<_>>();
+                prop_assert_eq!(metadata, expected);
+            }
+
+            #[test]
+            fn round_trips(pairs in arb_pairs()) {
+                let buf = encode(&pairs);
+                let metadata = extract_metadata_into_vec(&buf).unwrap();
+                let expected = pairs
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), value.as_str()))
+                    .collect::<Vec<_>>();
+                prop_assert_eq!(metadata, expected);
+            }
+        }
     }
 }
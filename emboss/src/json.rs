@@ -0,0 +1,234 @@
+//! Just enough of a JSON parser for the objects held by package metadata notes
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// How deeply arrays and objects may be nested within the note, so that a hostile one can't
+/// exhaust the stack
+const MAX_DEPTH: usize = 32;
+
+/// Parse a JSON object into the members that have a single value, in order
+///
+/// Strings are unescaped, while numbers and booleans are kept as they were written. Members
+/// that are `null`, or that hold an array or another object, are skipped. On failure, the
+/// offset at which the JSON stopped making sense is returned.
+pub(crate) fn parse_object(json: &str) -> Result<Vec<(String, String)>, usize> {
+    let mut parser = Parser { json, pos: 0 };
+    let mut pairs = Vec::new();
+    parser
+        .object(0, &mut |key, value| {
+            if let Some(value) = value {
+                pairs.push((key, value));
+            }
+        })
+        .ok_or(parser.pos)?;
+
+    parser.skip_whitespace();
+    match parser.pos == json.len() {
+        true => Ok(pairs),
+        false => Err(parser.pos),
+    }
+}
+
+struct Parser<'a> {
+    json: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn object(
+        &mut self,
+        depth: usize,
+        member: &mut impl FnMut(String, Option<String>),
+    ) -> Option<()> {
+        self.expect(b'{')?;
+        if self.eat(b'}') {
+            return Some(());
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(b':')?;
+            let value = self.value(depth)?;
+            member(key, value);
+
+            if self.eat(b'}') {
+                return Some(());
+            }
+
+            self.expect(b',')?;
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Option<()> {
+        self.expect(b'[')?;
+        if self.eat(b']') {
+            return Some(());
+        }
+
+        loop {
+            self.value(depth)?;
+
+            if self.eat(b']') {
+                return Some(());
+            }
+
+            self.expect(b',')?;
+        }
+    }
+
+    /// Parse a value, which is `None` when it isn't one that's kept
+    fn value(&mut self, depth: usize) -> Option<Option<String>> {
+        self.skip_whitespace();
+        match self.peek()? {
+            b'"' => return self.string().map(Some),
+            b'{' | b'[' if depth == MAX_DEPTH => return None,
+            b'{' => return self.object(depth + 1, &mut |_, _| {}).map(|_| None),
+            b'[' => return self.array(depth + 1).map(|_| None),
+            _ => {}
+        }
+
+        let rest = &self.json[self.pos..];
+        let len = rest
+            .find([',', '}', ']', ' ', '\t', '\n', '\r'])
+            .unwrap_or(rest.len());
+        let literal = &rest[..len];
+        let value = match literal {
+            "null" => None,
+            "true" | "false" => Some(literal.to_string()),
+            _ if is_number(literal) => Some(literal.to_string()),
+            _ => return None,
+        };
+
+        self.pos += len;
+        Some(value)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.json.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        self.skip_whitespace();
+        let found = self.peek() == Some(byte);
+        if found {
+            self.pos += 1;
+        }
+
+        found
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        self.eat(byte).then_some(())
+    }
+
+    fn string(&mut self) -> Option<String> {
+        if self.peek()? != b'"' {
+            return None;
+        }
+
+        self.pos += 1;
+        let mut string = String::new();
+        loop {
+            let c = self.json[self.pos..].chars().next()?;
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Some(string),
+                '\\' => string.push(self.escape()?),
+                c if c < ' ' => return None,
+                c => string.push(c),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Option<char> {
+        let escaped = self.peek()?;
+        self.pos += 1;
+        let c = match escaped {
+            b'"' => '"',
+            b'\\' => '\\',
+            b'/' => '/',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'u' => {
+                let high = self.hex()?;
+                if !(0xD800..0xDC00).contains(&high) {
+                    return char::from_u32(high);
+                }
+
+                // Characters outside the Basic Multilingual Plane are written as surrogate pairs
+                self.json[self.pos..].starts_with("\\u").then_some(())?;
+                self.pos += 2;
+                let low = self.hex()?;
+                if !(0xDC00..0xE000).contains(&low) {
+                    return None;
+                }
+
+                return char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00));
+            }
+            _ => return None,
+        };
+
+        Some(c)
+    }
+
+    fn hex(&mut self) -> Option<u32> {
+        let digits = self.json.get(self.pos..self.pos + 4)?;
+        if !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        self.pos += 4;
+        u32::from_str_radix(digits, 16).ok()
+    }
+}
+
+fn is_number(literal: &str) -> bool {
+    let starts_well = literal.starts_with(|c: char| c == '-' || c.is_ascii_digit());
+    let ends_well = literal.ends_with(|c: char| c.is_ascii_digit());
+    let only_number_chars = literal
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'));
+
+    starts_well && ends_well && only_number_chars && literal.parse::<f64>().is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, vec};
+
+    use super::*;
+
+    #[test]
+    fn skip_values_without_a_single_value() {
+        let json = r#"{"a": null, "b": {"c": [1, {"d": null}], "e": "f"}, "g": [], "h": "i"}"#;
+        assert_eq!(
+            parse_object(json),
+            Ok(vec![("h".to_string(), "i".to_string())])
+        );
+
+        // Skipped values still have to be valid
+        assert_eq!(parse_object(r#"{"a": {"b"}, "c": "d"}"#), Err(10));
+        assert_eq!(parse_object(r#"{"a": [1,], "c": "d"}"#), Err(9));
+    }
+
+    #[test]
+    fn limit_nesting() {
+        let nested =
+            |depth: usize| format!("{{\"a\": {}1{}}}", "[".repeat(depth), "]".repeat(depth));
+        assert_eq!(parse_object(&nested(MAX_DEPTH)), Ok(vec![]));
+        assert_eq!(parse_object(&nested(MAX_DEPTH + 1)), Err(6 + MAX_DEPTH));
+    }
+}
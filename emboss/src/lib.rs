@@ -1,5 +1,38 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+// Lets the code generated by the emboss macros refer to `::emboss` from within this crate
+extern crate self as emboss;
+
+#[cfg(feature = "serde")]
+pub mod de;
+#[cfg(feature = "alloc")]
+pub mod encode;
 pub mod error;
 pub mod extract;
+#[cfg(feature = "alloc")]
+mod json;
+#[cfg(feature = "alloc")]
+pub mod metadata;
+#[cfg(feature = "object")]
+pub mod read;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "illumos",
+    target_os = "solaris"
+))]
+pub mod runtime;
+pub mod value;
 
 pub use emboss_common::*;
 pub use emboss_macros::*;
+#[cfg(feature = "alloc")]
+pub use metadata::EmbossedMetadata;
+pub use value::EmbossValue;
@@ -0,0 +1,335 @@
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+use crate::{
+    error::{EmbossError, ErrorKind},
+    json,
+};
+
+/// Embossed key-value pairs that own their data
+///
+/// Pairs are kept in the order they were embossed, and a key may appear more than once, as
+/// happens when several blocks in a section emboss the same key. Use
+/// [`crate::extract::extract_metadata_into_owned`] to read one from a section.
+///
+/// # Example
+///
+/// ```rust
+/// use emboss::EmbossedMetadata;
+///
+/// let data = b"\x55\xB0\x77\x1A\x02version\x001.2.3\0commit\09fceb02\0";
+/// let metadata = emboss::extract::extract_metadata_into_owned(data).unwrap();
+///
+/// assert_eq!(metadata.get("version"), Some("1.2.3"));
+/// assert_eq!(metadata.to_string(), "version: 1.2.3\ncommit: 9fceb02");
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EmbossedMetadata {
+    pairs: Vec<(String, String)>,
+}
+
+impl EmbossedMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The value of the first pair with the given key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.get_all(key).next()
+    }
+
+    /// The values of every pair with the given key, in embossing order
+    pub fn get_all(&self, key: &str) -> impl Iterator<Item = &str> {
+        self.iter()
+            .filter(move |(pair_key, _)| *pair_key == key)
+            .map(|(_, value)| value)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Iterate over every pair, in embossing order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.pairs
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Add a pair after all of the existing ones, keeping any earlier pair with the same key
+    pub fn push(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.pairs.push((key.into(), value.into()));
+    }
+
+    /// Append every pair of `other`, as if its blocks followed this one's in the section
+    pub fn merge(&mut self, other: EmbossedMetadata) {
+        self.pairs.extend(other.pairs);
+    }
+
+    /// Decode the descriptor of a `.note.package` ELF note, as described by
+    /// <https://systemd.io/ELF_PACKAGE_METADATA/>
+    ///
+    /// Each member of the JSON object becomes a pair, in order. Numbers and booleans are kept
+    /// as they were written, while members that are `null`, or hold an array or another
+    /// object, are skipped. The NUL terminator and padding after the object are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use emboss::EmbossedMetadata;
+    ///
+    /// let desc = b"{\"type\":\"rpm\",\"name\":\"coreutils\",\"version\":\"9.3-1\"}\0\0\0";
+    /// let metadata = EmbossedMetadata::from_package_note(desc).unwrap();
+    ///
+    /// assert_eq!(metadata.get("name"), Some("coreutils"));
+    /// assert_eq!(metadata.get("version"), Some("9.3-1"));
+    /// ```
+    pub fn from_package_note(desc: &[u8]) -> Result<Self, EmbossError> {
+        let len = desc
+            .iter()
+            .rposition(|byte| *byte != 0)
+            .map_or(0, |last| last + 1);
+        let json = core::str::from_utf8(&desc[..len]).map_err(ErrorKind::InvalidUtf8)?;
+
+        json::parse_object(json)
+            .map(Self::from_iter)
+            .map_err(|pos| EmbossError::from(ErrorKind::InvalidPackageNote).at(pos))
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for EmbossedMetadata
+where
+    K: Into<String>,
+    V: Into<String>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self {
+            pairs: iter
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        }
+    }
+}
+
+impl IntoIterator for EmbossedMetadata {
+    type Item = (String, String);
+    type IntoIter = alloc::vec::IntoIter<(String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pairs.into_iter()
+    }
+}
+
+/// Formats each pair as `key: value`, one per line
+impl fmt::Display for EmbossedMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (key, value)) in self.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+
+            write!(f, "{}: {}", key, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Serialized as a map, in embossing order and keeping any duplicate keys
+#[cfg(feature = "serde")]
+impl serde::Serialize for EmbossedMetadata {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (key, value) in self.iter() {
+            map.serialize_entry(key, value)?;
+        }
+
+        map.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for EmbossedMetadata {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MetadataVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for MetadataVisitor {
+            type Value = EmbossedMetadata;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a map of embossed keys to values")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut metadata = EmbossedMetadata::new();
+                while let Some((key, value)) = map.next_entry::<String, String>()? {
+                    metadata.push(key, value);
+                }
+
+                Ok(metadata)
+            }
+        }
+
+        deserializer.deserialize_map(MetadataVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec};
+
+    use super::*;
+
+    fn metadata() -> EmbossedMetadata {
+        [
+            ("version", "1.2.3"),
+            ("target", "x86_64"),
+            ("version", "1.2.4"),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn get_duplicate_keys() {
+        let metadata = metadata();
+        assert_eq!(metadata.get("version"), Some("1.2.3"));
+        assert_eq!(
+            metadata.get_all("version").collect::<Vec<_>>(),
+            vec!["1.2.3", "1.2.4"]
+        );
+        assert_eq!(metadata.get("missing"), None);
+        assert!(metadata.contains_key("target"));
+        assert_eq!(metadata.len(), 3);
+    }
+
+    #[test]
+    fn merge_keeps_order() {
+        let mut metadata = metadata();
+        metadata.merge([("commit", "9fceb02")].into_iter().collect());
+        assert_eq!(
+            metadata.iter().collect::<Vec<_>>(),
+            vec![
+                ("version", "1.2.3"),
+                ("target", "x86_64"),
+                ("version", "1.2.4"),
+                ("commit", "9fceb02"),
+            ]
+        );
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            metadata().to_string(),
+            "version: 1.2.3\ntarget: x86_64\nversion: 1.2.4"
+        );
+        assert_eq!(EmbossedMetadata::new().to_string(), "");
+    }
+
+    #[test]
+    fn package_note() {
+        let desc = br#" { "type": "rpm", "name": "emboss\u00e9\ud83d\ude00", "os": "fedora",
+            "osVersion": 39, "debug": false, "path": "a\/b\"c\n" }"#;
+        let metadata = EmbossedMetadata::from_package_note(&[&desc[..], b"\0\0"].concat());
+        assert_eq!(
+            metadata.unwrap().iter().collect::<Vec<_>>(),
+            vec![
+                ("type", "rpm"),
+                ("name", "emboss\u{e9}\u{1F600}"),
+                ("os", "fedora"),
+                ("osVersion", "39"),
+                ("debug", "false"),
+                ("path", "a/b\"c\n"),
+            ]
+        );
+
+        assert_eq!(
+            EmbossedMetadata::from_package_note(b"{}\0"),
+            Ok(EmbossedMetadata::new())
+        );
+    }
+
+    #[test]
+    fn package_note_fedora() {
+        // A note as Fedora writes it, along with members that other generators may add
+        let desc = br#"{"type":"rpm","name":"coreutils","version":"9.3-4.fc39","architecture":"x86_64","osCpe":"cpe:/o:fedoraproject:fedora:39","debugInfoUrl":null,"buildId":42,"signed":true,"extra":{"vendor":"Fedora Project"},"tags":["core"]}"#;
+        let desc = [&desc[..], b"\0\0\0"].concat();
+
+        let metadata = EmbossedMetadata::from_package_note(&desc).unwrap();
+        assert_eq!(
+            metadata.iter().collect::<Vec<_>>(),
+            vec![
+                ("type", "rpm"),
+                ("name", "coreutils"),
+                ("version", "9.3-4.fc39"),
+                ("architecture", "x86_64"),
+                ("osCpe", "cpe:/o:fedoraproject:fedora:39"),
+                ("buildId", "42"),
+                ("signed", "true"),
+            ]
+        );
+    }
+
+    #[test]
+    fn package_note_fail() {
+        let kind = |desc: &[u8]| {
+            EmbossedMetadata::from_package_note(desc).map_err(|err| (err.offset(), err.into_kind()))
+        };
+
+        assert_eq!(
+            kind(br#"{"a": "b", "c": {"d" "e"}}"#),
+            Err((Some(21), ErrorKind::InvalidPackageNote))
+        );
+        for desc in [
+            &br#"["a"]"#[..],
+            br#"{"a": "b",}"#,
+            br#"{"a": "b"} x"#,
+            br#"{"a": nil}"#,
+            br#"{"a": inf}"#,
+            br#"{"a": "\ud83d"}"#,
+            b"{\"a\": \"\n\"}",
+            br#"{"a": "b""#,
+            b"",
+        ] {
+            assert_eq!(
+                kind(desc).map_err(|(_, kind)| kind),
+                Err(ErrorKind::InvalidPackageNote),
+                "{}",
+                String::from_utf8_lossy(desc)
+            );
+        }
+
+        assert!(matches!(
+            kind(b"{\"a\": \"\xFF\"}"),
+            Err((None, ErrorKind::InvalidUtf8(_)))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let json = serde_json::to_string(&metadata()).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":"1.2.3","target":"x86_64","version":"1.2.4"}"#
+        );
+
+        let metadata: EmbossedMetadata = serde_json::from_str(&json).unwrap();
+        assert_eq!(metadata, self::metadata());
+    }
+}
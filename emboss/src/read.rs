@@ -0,0 +1,540 @@
+use std::{fmt, io, path::Path};
+
+use emboss_common::{
+    NOTE_NAME, NOTE_SECTION_NAME, NOTE_TYPE, PACKAGE_NOTE_NAME, PACKAGE_NOTE_TYPE,
+};
+use object::{
+    Object, ObjectSection, ReadRef,
+    read::elf::{ElfFile, FileHeader, ProgramHeader},
+};
+
+use crate::{
+    error::EmbossError,
+    extract::{
+        EmbossIter, ExtractOptions, TypedEmbossIter, extract_metadata_into_owned_with_options,
+        iter_typed_with_options, iter_with_options,
+    },
+    metadata::EmbossedMetadata,
+};
+
+/// An error from reading the sections of an object file
+#[derive(Debug)]
+pub enum Error {
+    /// The file could not be read
+    Io(io::Error),
+    /// The file is not an object file that could be parsed
+    Object(object::Error),
+    /// No section had the name that was asked for
+    SectionNotFound(String),
+    /// A package metadata note could not be decoded
+    PackageNote(EmbossError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => err.fmt(f),
+            Error::Object(err) => err.fmt(f),
+            Error::SectionNotFound(section) => {
+                write!(f, "No section named '{}' was found", section)
+            }
+            Error::PackageNote(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Object(err) => Some(err),
+            Error::SectionNotFound(_) => None,
+            Error::PackageNote(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<object::Error> for Error {
+    fn from(err: object::Error) -> Self {
+        Error::Object(err)
+    }
+}
+
+/// An embossed section of an object file, along with where it was found
+///
+/// The data is only parsed when asked for, with the [`ExtractOptions`] it was read with, so a
+/// section that fails to parse doesn't keep the others from being read.
+///
+/// Blocks embossed with `as_note` are found through the program headers of ELF files, so
+/// they're read even once the section headers are stripped. Each one is returned on its own,
+/// named `.note.emboss`, with the location of the block within its note.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EmbossedSection {
+    /// The name of the section, without the segment it belongs to on Mach-O
+    pub name: String,
+    /// Where the section starts in the file, or `None` if it takes up no space in it
+    pub file_offset: Option<u64>,
+    /// The address the section is loaded at
+    pub address: u64,
+    /// The raw contents of the section
+    pub data: Vec<u8>,
+    options: ExtractOptions,
+}
+
+impl EmbossedSection {
+    /// Parse the pairs embossed in the section, decompressing values with the `deflate`
+    /// feature
+    pub fn metadata(&self) -> Result<EmbossedMetadata, EmbossError> {
+        extract_metadata_into_owned_with_options(&self.data, &self.options)
+    }
+
+    /// Lazily iterate over the pairs embossed in the section
+    pub fn iter(&self) -> EmbossIter<'_> {
+        iter_with_options(&self.data, &self.options)
+    }
+
+    /// Lazily iterate over the pairs embossed in the section, with typed values
+    pub fn iter_typed(&self) -> TypedEmbossIter<'_> {
+        iter_typed_with_options(&self.data, &self.options)
+    }
+}
+
+/// Options that control which sections are read, and how
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ReadOptions<'a> {
+    /// Only read the section with this name, failing if there is none
+    ///
+    /// By default, every section is scanned and those starting with an accepted magic
+    /// number are read, whatever they're called.
+    pub section_name: Option<&'a str>,
+
+    /// How the data in each section is parsed, including which magic numbers start a block
+    pub extract: ExtractOptions,
+}
+
+/// Read the embossed sections of the object file at `path`
+pub fn read_file(path: impl AsRef<Path>) -> Result<Vec<EmbossedSection>, Error> {
+    read_file_with_options(path, &ReadOptions::default())
+}
+
+/// Read the embossed sections of the object file at `path`, using the given options
+pub fn read_file_with_options(
+    path: impl AsRef<Path>,
+    options: &ReadOptions<'_>,
+) -> Result<Vec<EmbossedSection>, Error> {
+    let data = std::fs::read(path)?;
+    read_bytes_with_options(&data, options)
+}
+
+/// Read the embossed sections of the running executable
+///
+/// # Example
+///
+/// ```rust
+/// emboss::emboss!(key = "version", value = "1.2.3", stored_in = "__emboss_read_doc");
+///
+/// let sections = emboss::read::read_current_exe().unwrap();
+/// let section = sections
+///     .iter()
+///     .find(|section| section.name == "__emboss_read_doc")
+///     .unwrap();
+/// assert_eq!(section.metadata().unwrap().get("version"), Some("1.2.3"));
+/// ```
+pub fn read_current_exe() -> Result<Vec<EmbossedSection>, Error> {
+    read_current_exe_with_options(&ReadOptions::default())
+}
+
+/// Read the embossed sections of the running executable, using the given options
+pub fn read_current_exe_with_options(
+    options: &ReadOptions<'_>,
+) -> Result<Vec<EmbossedSection>, Error> {
+    read_file_with_options(std::env::current_exe()?, options)
+}
+
+/// Read the embossed sections of an object file that is already in memory
+pub fn read_bytes(data: &[u8]) -> Result<Vec<EmbossedSection>, Error> {
+    read_bytes_with_options(data, &ReadOptions::default())
+}
+
+/// Read the embossed sections of an object file that is already in memory, using the given
+/// options
+pub fn read_bytes_with_options(
+    data: &[u8],
+    options: &ReadOptions<'_>,
+) -> Result<Vec<EmbossedSection>, Error> {
+    let file = object::File::parse(data)?;
+
+    if let Some(name) = options.section_name {
+        let section = file
+            .section_by_name(name)
+            .ok_or_else(|| Error::SectionNotFound(name.to_string()))?;
+        return Ok(vec![read_section(&section, &options.extract)?]);
+    }
+
+    let mut sections = Vec::new();
+    for section in file.sections() {
+        // Most sections have nothing to do with emboss, so one that can't be read shouldn't
+        // keep the others from being found
+        let Ok(data) = section.data() else {
+            continue;
+        };
+
+        let magic = data.first_chunk::<4>();
+        if magic.is_some_and(|magic| options.extract.magic.matches(magic)) {
+            sections.push(read_section(&section, &options.extract)?);
+        }
+    }
+
+    for note in elf_notes(&file, NOTE_NAME, NOTE_TYPE) {
+        let magic = note.desc.first_chunk::<4>();
+        if magic.is_some_and(|magic| options.extract.magic.matches(magic)) {
+            sections.push(EmbossedSection {
+                name: NOTE_SECTION_NAME.to_string(),
+                file_offset: Some(note.file_offset),
+                address: note.address,
+                data: note.desc.to_vec(),
+                options: options.extract,
+            });
+        }
+    }
+
+    Ok(sections)
+}
+
+/// Read the package metadata note of the object file at `path`, if it has one
+///
+/// The note is embossed with `emboss_package_note!`, or by distribution tooling, and is found
+/// through the program headers of ELF files like notes embossed with `as_note` are.
+pub fn read_package_note_file(path: impl AsRef<Path>) -> Result<Option<EmbossedMetadata>, Error> {
+    let data = std::fs::read(path)?;
+    read_package_note_bytes(&data)
+}
+
+/// Read the package metadata note of the running executable, if it has one
+pub fn read_package_note_current_exe() -> Result<Option<EmbossedMetadata>, Error> {
+    read_package_note_file(std::env::current_exe()?)
+}
+
+/// Read the package metadata note of an object file that is already in memory, if it has one
+///
+/// Only the first note is read, should there be more than one.
+pub fn read_package_note_bytes(data: &[u8]) -> Result<Option<EmbossedMetadata>, Error> {
+    let file = object::File::parse(data)?;
+    let notes = elf_notes(&file, PACKAGE_NOTE_NAME, PACKAGE_NOTE_TYPE);
+    notes
+        .first()
+        .map(|note| EmbossedMetadata::from_package_note(note.desc))
+        .transpose()
+        .map_err(Error::PackageNote)
+}
+
+/// The descriptor of an ELF note, along with where it was found
+struct ElfNote<'data> {
+    desc: &'data [u8],
+    file_offset: u64,
+    address: u64,
+}
+
+/// The notes in an ELF file with the given owner and type
+///
+/// Segments whose notes can't be parsed are skipped from the first bad note on.
+fn elf_notes<'data>(file: &object::File<'data>, name: &str, kind: u32) -> Vec<ElfNote<'data>> {
+    let mut notes = Vec::new();
+    match file {
+        object::File::Elf32(elf) => collect_elf_notes(elf, name, kind, &mut notes),
+        object::File::Elf64(elf) => collect_elf_notes(elf, name, kind, &mut notes),
+        _ => {}
+    }
+
+    notes
+}
+
+fn collect_elf_notes<'data, Elf: FileHeader, R: ReadRef<'data>>(
+    file: &ElfFile<'data, Elf, R>,
+    name: &str,
+    kind: u32,
+    found: &mut Vec<ElfNote<'data>>,
+) {
+    let endian = file.endian();
+    for segment in file.elf_program_headers() {
+        // Like sections, one segment that can't be read shouldn't hide the notes in the others
+        let Ok(Some(mut notes)) = segment.notes(endian, file.data()) else {
+            continue;
+        };
+
+        // Always there, as the notes were read from it
+        let Ok(data) = segment.data(endian, file.data()) else {
+            continue;
+        };
+
+        while let Ok(Some(note)) = notes.next() {
+            if note.name() != name.as_bytes() || note.n_type(endian) != kind {
+                continue;
+            }
+
+            // The descriptor always lies within the segment's data
+            let desc = note.desc();
+            let offset = desc.as_ptr() as u64 - data.as_ptr() as u64;
+            found.push(ElfNote {
+                desc,
+                file_offset: segment.p_offset(endian).into() + offset,
+                address: segment.p_vaddr(endian).into() + offset,
+            });
+        }
+    }
+}
+
+fn read_section<'data>(
+    section: &impl ObjectSection<'data>,
+    options: &ExtractOptions,
+) -> Result<EmbossedSection, Error> {
+    Ok(EmbossedSection {
+        name: section.name()?.to_string(),
+        file_offset: section.file_range().map(|(offset, _)| offset),
+        address: section.address(),
+        data: section.data()?.to_vec(),
+        options: *options,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    emboss_macros::emboss_many!(
+        items = [
+            { key = "version", value = "1.2.3" },
+            { key = "commit", value = "9fceb02" },
+        ],
+        stored_in = "__emboss_read"
+    );
+
+    emboss_macros::emboss!(
+        key = "board",
+        value = "rev-c",
+        stored_in = "__emboss_read_magic",
+        magic = 0x5EC7_10A7
+    );
+
+    emboss_macros::emboss!(
+        key = "blob",
+        value = b"\xDE\xAD\xBE\xEF",
+        stored_in = "__emboss_read_bytes"
+    );
+
+    #[cfg(target_os = "linux")]
+    emboss_macros::emboss!(key = "noted", value = "yes", as_note = true);
+
+    #[cfg(target_os = "linux")]
+    emboss_macros::emboss_many!(
+        items = [
+            { key = "noted", value = "also" },
+            { key = "count", value = 2 },
+        ],
+        as_note = true,
+        checksum = false
+    );
+
+    #[cfg(target_os = "linux")]
+    emboss_macros::emboss_package_note!(items = [
+        { key = "type", value = "test" },
+        { key = "debugInfoUrl", value = "https://debuginfod.example.com/" },
+    ]);
+
+    fn find<'a>(sections: &'a [EmbossedSection], name: &str) -> Option<&'a EmbossedSection> {
+        sections.iter().find(|section| section.name == name)
+    }
+
+    #[test]
+    fn read_scans_every_section() {
+        let sections = read_current_exe().unwrap();
+        let section = find(&sections, "__emboss_read").unwrap();
+        let metadata = section.metadata().unwrap();
+        assert_eq!(metadata.get("version"), Some("1.2.3"));
+        assert_eq!(metadata.get("commit"), Some("9fceb02"));
+        assert!(section.file_offset.is_some());
+        assert_ne!(section.address, 0);
+
+        // Sections holding bytes that can't be read as strings are found too, but not ones
+        // with another magic
+        let section = find(&sections, "__emboss_read_bytes").unwrap();
+        assert!(section.metadata().is_err());
+        assert!(section.iter_typed().all(|pair| pair.is_ok()));
+        assert!(find(&sections, "__emboss_read_magic").is_none());
+    }
+
+    #[test]
+    fn read_section_by_name() {
+        let data = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        let options = ReadOptions {
+            section_name: Some("__emboss_read"),
+            ..Default::default()
+        };
+        let sections = read_bytes_with_options(&data, &options).unwrap();
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].name, "__emboss_read");
+
+        // The section's data lives at its file offset
+        let offset = sections[0].file_offset.unwrap() as usize;
+        assert_eq!(data[offset..offset + 4], 0x1A77B055u32.to_le_bytes());
+    }
+
+    #[test]
+    fn read_custom_magic() {
+        let options = ReadOptions {
+            extract: ExtractOptions {
+                magic: crate::extract::Magic::Exactly(0x5EC7_10A7),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let sections = read_current_exe_with_options(&options).unwrap();
+        let section = find(&sections, "__emboss_read_magic").unwrap();
+        assert_eq!(section.metadata().unwrap().get("board"), Some("rev-c"));
+        assert!(find(&sections, "__emboss_read").is_none());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn read_notes_through_program_headers() {
+        let data = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        let notes = read_bytes(&data)
+            .unwrap()
+            .into_iter()
+            .filter(|section| section.name == NOTE_SECTION_NAME)
+            .collect::<Vec<_>>();
+
+        let mut values = notes
+            .iter()
+            .filter_map(|note| note.metadata().unwrap().get("noted").map(str::to_string))
+            .collect::<Vec<_>>();
+        values.sort();
+        assert_eq!(values, ["also", "yes"]);
+
+        // Each note holds exactly one block, at its file offset
+        for note in &notes {
+            assert_eq!(crate::extract::blocks(&note.data).count(), 1);
+            let offset = note.file_offset.unwrap() as usize;
+            assert_eq!(data[offset..offset + note.data.len()], note.data);
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn read_package_note() {
+        let metadata = read_package_note_current_exe().unwrap().unwrap();
+        assert_eq!(metadata.get("type"), Some("test"));
+        assert_eq!(metadata.get("name"), Some("emboss"));
+        assert_eq!(metadata.get("version"), Some(env!("CARGO_PKG_VERSION")));
+        assert_eq!(
+            metadata.get("debugInfoUrl"),
+            Some("https://debuginfod.example.com/")
+        );
+
+        // The defaults keep their place, with the other keys after them
+        let keys = metadata.iter().map(|(key, _)| key).collect::<Vec<_>>();
+        assert_eq!(keys[..3], ["type", "name", "version"]);
+        assert_eq!(keys.last(), Some(&"debugInfoUrl"));
+
+        // The OS of the build environment is only added when asked for
+        assert_eq!(metadata.get("os"), None);
+
+        // It isn't mistaken for a block
+        let sections = read_current_exe().unwrap();
+        assert!(
+            sections
+                .iter()
+                .all(|section| section.name != ".note.package")
+        );
+    }
+
+    #[test]
+    #[cfg(all(
+        target_os = "linux",
+        target_pointer_width = "64",
+        target_endian = "little"
+    ))]
+    fn read_skips_unreadable_sections() {
+        let mut data = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        let index = object::File::parse(&*data)
+            .unwrap()
+            .section_by_name("__emboss_read_magic")
+            .unwrap()
+            .index()
+            .0;
+
+        // Point the section's data past the end of the file
+        let u64_at =
+            |data: &[u8], at: usize| u64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+        let header = u64_at(&data, 0x28) as usize + index * 64;
+        data[header + 24..header + 32].copy_from_slice(&u64::MAX.to_le_bytes());
+
+        let sections = read_bytes(&data).unwrap();
+        assert!(find(&sections, "__emboss_read").is_some());
+
+        // Unless it's the section that was asked for
+        let options = ReadOptions {
+            section_name: Some("__emboss_read_magic"),
+            ..Default::default()
+        };
+        assert!(matches!(
+            read_bytes_with_options(&data, &options),
+            Err(Error::Object(_))
+        ));
+    }
+
+    #[test]
+    #[cfg(all(
+        target_os = "linux",
+        target_pointer_width = "64",
+        target_endian = "little"
+    ))]
+    fn read_skips_unreadable_note_segments() {
+        let mut data = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+
+        // Point the data of every note segment past the end of the file
+        let u16_at =
+            |data: &[u8], at: usize| u16::from_le_bytes(data[at..at + 2].try_into().unwrap());
+        let u64_at =
+            |data: &[u8], at: usize| u64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+        let headers = u64_at(&data, 0x20) as usize;
+        for index in 0..u16_at(&data, 0x38) as usize {
+            let header = headers + index * 56;
+            if data[header..header + 4] == 4u32.to_le_bytes() {
+                data[header + 8..header + 16].copy_from_slice(&u64::MAX.to_le_bytes());
+            }
+        }
+
+        let sections = read_bytes(&data).unwrap();
+        assert!(find(&sections, "__emboss_read").is_some());
+        assert!(find(&sections, NOTE_SECTION_NAME).is_none());
+        assert!(read_package_note_bytes(&data).unwrap().is_none());
+    }
+
+    #[test]
+    fn read_fail() {
+        let options = ReadOptions {
+            section_name: Some("__emboss_missing"),
+            ..Default::default()
+        };
+        assert!(matches!(
+            read_current_exe_with_options(&options),
+            Err(Error::SectionNotFound(name)) if name == "__emboss_missing"
+        ));
+
+        assert!(matches!(
+            read_bytes(b"not an object file"),
+            Err(Error::Object(_))
+        ));
+        assert!(matches!(
+            read_file("/nonexistent/emboss"),
+            Err(Error::Io(err)) if err.kind() == io::ErrorKind::NotFound
+        ));
+    }
+}
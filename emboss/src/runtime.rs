@@ -0,0 +1,404 @@
+//! Reading the blocks linked into the running process, without touching the filesystem
+//!
+//! On ELF targets, the linker defines `__start_<section>` and `__stop_<section>` around every
+//! section whose name is a valid C identifier. Blocks embossed into
+//! [`RUNTIME_SECTION_NAME`](crate::RUNTIME_SECTION_NAME) by any crate, including dependencies,
+//! end up next to each other between those two symbols.
+//!
+//! Those symbols only cover the object they're linked into, so on Linux
+//! [`loaded_modules`] also finds the blocks embossed with `as_note` in every shared object
+//! loaded into the process.
+
+#[cfg(all(target_os = "linux", feature = "std"))]
+use std::{
+    ffi::{CStr, c_int, c_void},
+    ops::Range,
+    path::PathBuf,
+};
+
+#[cfg(all(target_os = "linux", feature = "std"))]
+use emboss_common::{NOTE_NAME, NOTE_TYPE};
+
+use crate::extract::{Blocks, ExtractOptions, padded_blocks_with_options};
+#[cfg(all(target_os = "linux", feature = "std"))]
+use crate::{
+    error::EmbossError,
+    extract::{
+        EmbossIter, TypedEmbossIter, blocks_with_options, extract_metadata_into_owned_with_options,
+        iter_typed_with_options, iter_with_options,
+    },
+    metadata::EmbossedMetadata,
+};
+
+// Makes sure the section exists, so the linker defines its bounds even when nothing was
+// embossed into it. Being zero, it is skipped as padding wherever it ends up.
+#[used]
+#[unsafe(link_section = "emboss_meta")]
+static ANCHOR: [u8; 1] = [0];
+
+unsafe extern "C" {
+    static __start_emboss_meta: u8;
+    static __stop_emboss_meta: u8;
+}
+
+/// The raw contents of the runtime section, as linked into the running process
+pub fn section() -> &'static [u8] {
+    let start = &raw const __start_emboss_meta;
+    let stop = &raw const __stop_emboss_meta;
+
+    // SAFETY: The linker lays out the whole section between the two symbols, and it is
+    // only ever read from
+    unsafe { core::slice::from_raw_parts(start, stop as usize - start as usize) }
+}
+
+/// Iterate over every block embossed into the runtime section by the crates linked into the
+/// running process
+///
+/// Blocks come in the order the linker laid them out in, which needn't match the order
+/// they were embossed in.
+///
+/// Only blocks embossed with `stored_in = "emboss_meta"` are visible here. The macro stores
+/// blocks in `.emboss.meta` by default, and since that name isn't a valid C identifier, the
+/// linker marks no bounds around it; those blocks can only be read from the binary's file.
+///
+/// # Example
+///
+/// ```rust
+/// emboss::emboss!(key = "version", value = "1.2.3", stored_in = "emboss_meta");
+///
+/// let found = emboss::runtime::all_blocks()
+///     .map(|block| block.unwrap())
+///     .any(|block| block.iter().flatten().any(|pair| pair == ("version", "1.2.3")));
+/// assert!(found);
+/// ```
+pub fn all_blocks() -> Blocks<'static> {
+    all_blocks_with_options(&ExtractOptions::default())
+}
+
+/// Iterate over every block embossed into the runtime section, using the given options
+pub fn all_blocks_with_options(options: &ExtractOptions) -> Blocks<'static> {
+    padded_blocks_with_options(section(), options)
+}
+
+/// An object loaded into the running process, along with the blocks found in its memory
+///
+/// The blocks are copied out while the object is known to be loaded, so they stay readable
+/// after it is unloaded, and don't depend on the file it was loaded from.
+#[cfg(all(target_os = "linux", feature = "std"))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LoadedModule {
+    /// The path the dynamic loader opened the object from, or the path of the running
+    /// executable for the main program
+    pub path: PathBuf,
+    /// The address the object was loaded at, which its virtual addresses are relative to
+    pub address: usize,
+    /// Every block found in the object, one after the other
+    pub data: Vec<u8>,
+    options: ExtractOptions,
+}
+
+#[cfg(all(target_os = "linux", feature = "std"))]
+impl LoadedModule {
+    /// Parse the pairs embossed in the object, decompressing values with the `deflate` feature
+    pub fn metadata(&self) -> Result<EmbossedMetadata, EmbossError> {
+        extract_metadata_into_owned_with_options(&self.data, &self.options)
+    }
+
+    /// Lazily iterate over the pairs embossed in the object
+    pub fn iter(&self) -> EmbossIter<'_> {
+        iter_with_options(&self.data, &self.options)
+    }
+
+    /// Lazily iterate over the pairs embossed in the object, with typed values
+    pub fn iter_typed(&self) -> TypedEmbossIter<'_> {
+        iter_typed_with_options(&self.data, &self.options)
+    }
+
+    /// Iterate over each block embossed in the object
+    pub fn blocks(&self) -> Blocks<'_> {
+        blocks_with_options(&self.data, &self.options)
+    }
+}
+
+/// Find the blocks embossed with `as_note` in every object loaded into the running process,
+/// including the main program and any shared objects opened with `dlopen`
+///
+/// The program headers of each object are walked with `dl_iterate_phdr`, and the blocks are
+/// read from the emboss notes in its `PT_NOTE` segments, so this works even when an object's
+/// file was replaced or removed after it was loaded. Blocks stored in ordinary sections can't
+/// be found this way. Objects without any emboss notes are left out.
+///
+/// # Example
+///
+/// ```rust
+/// emboss::emboss!(key = "plugin", value = "transcoder", as_note = true);
+///
+/// let found = emboss::runtime::loaded_modules()
+///     .iter()
+///     .filter_map(|module| module.metadata().ok())
+///     .any(|metadata| metadata.get("plugin") == Some("transcoder"));
+/// assert!(found);
+/// ```
+#[cfg(all(target_os = "linux", feature = "std"))]
+pub fn loaded_modules() -> Vec<LoadedModule> {
+    loaded_modules_with_options(&ExtractOptions::default())
+}
+
+/// Find the blocks of every object loaded into the running process, using the given options
+#[cfg(all(target_os = "linux", feature = "std"))]
+pub fn loaded_modules_with_options(options: &ExtractOptions) -> Vec<LoadedModule> {
+    let mut state = (*options, Vec::new());
+
+    // SAFETY: The callback only runs until `dl_iterate_phdr` returns, while `state` is alive
+    unsafe {
+        libc::dl_iterate_phdr(
+            Some(visit_module),
+            &mut state as *mut (ExtractOptions, Vec<LoadedModule>) as *mut c_void,
+        );
+    }
+
+    state.1
+}
+
+#[cfg(all(target_os = "linux", feature = "std"))]
+unsafe extern "C" fn visit_module(
+    info: *mut libc::dl_phdr_info,
+    _size: usize,
+    state: *mut c_void,
+) -> c_int {
+    // SAFETY: The loader hands over a valid description of the object, and holds its lock so
+    // the object can't be unloaded until this returns
+    let (info, (options, modules)) = unsafe {
+        (
+            &*info,
+            &mut *(state as *mut (ExtractOptions, Vec<LoadedModule>)),
+        )
+    };
+
+    let headers = match info.dlpi_phdr.is_null() {
+        true => &[][..],
+        // SAFETY: The loader points at `dlpi_phnum` program headers
+        false => unsafe { core::slice::from_raw_parts(info.dlpi_phdr, info.dlpi_phnum as usize) },
+    };
+
+    let mut data = Vec::new();
+    for header in headers {
+        if header.p_type != libc::PT_NOTE {
+            continue;
+        }
+
+        let start = (info.dlpi_addr as usize).wrapping_add(header.p_vaddr as usize);
+        // SAFETY: Note segments lie within a loaded segment, which is mapped and readable
+        // while the object is loaded
+        let segment =
+            unsafe { core::slice::from_raw_parts(start as *const u8, header.p_memsz as usize) };
+        find_notes(segment, header.p_align as usize, options, &mut data);
+    }
+
+    if !data.is_empty() {
+        let path = match info.dlpi_name.is_null() {
+            true => PathBuf::new(),
+            // SAFETY: The loader names the object with a NUL-terminated string
+            false => {
+                let name = unsafe { CStr::from_ptr(info.dlpi_name) };
+                PathBuf::from(name.to_string_lossy().into_owned())
+            }
+        };
+
+        // The main program is the only object the loader doesn't name
+        let path = match path.as_os_str().is_empty() {
+            true => std::env::current_exe().unwrap_or(path),
+            false => path,
+        };
+
+        modules.push(LoadedModule {
+            path,
+            address: info.dlpi_addr as usize,
+            data,
+            options: *options,
+        });
+    }
+
+    0
+}
+
+/// Copy the block of every emboss note in a note segment into `data`
+///
+/// Notes are laid out in the byte order of the running process, with their name and
+/// descriptor padded to the alignment of the segment.
+#[cfg(all(target_os = "linux", feature = "std"))]
+fn find_notes(segment: &[u8], align: usize, options: &ExtractOptions, data: &mut Vec<u8>) {
+    let align = if align == 8 { 8 } else { 4 };
+    let word = |pos: usize| {
+        segment
+            .get(pos..pos + 4)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(|bytes| u32::from_ne_bytes(bytes) as usize)
+    };
+
+    let mut pos = 0;
+    while let (Some(name_size), Some(desc_size), Some(kind)) =
+        (word(pos), word(pos + 4), word(pos + 8))
+    {
+        // Sizes as large as these can only come from a corrupt note, and may not even fit on
+        // 32-bit targets
+        let Some((name, desc, next)) = note_layout(pos, name_size, desc_size, align) else {
+            break;
+        };
+
+        let (Some(name), Some(desc)) = (segment.get(name), segment.get(desc)) else {
+            break;
+        };
+
+        let magic = desc.first_chunk::<4>();
+        if name.strip_suffix(b"\0") == Some(NOTE_NAME.as_bytes())
+            && kind == NOTE_TYPE as usize
+            && magic.is_some_and(|magic| options.magic.matches(magic))
+        {
+            data.extend_from_slice(desc);
+        }
+
+        pos = next;
+    }
+}
+
+/// Where the name and descriptor of the note at `pos` lie, and where the note after it starts
+#[cfg(all(target_os = "linux", feature = "std"))]
+fn note_layout(
+    pos: usize,
+    name_size: usize,
+    desc_size: usize,
+    align: usize,
+) -> Option<(Range<usize>, Range<usize>, usize)> {
+    let name_start = pos.checked_add(12)?;
+    let desc_start = name_start.checked_add(name_size.checked_next_multiple_of(align)?)?;
+    let next = desc_start.checked_add(desc_size.checked_next_multiple_of(align)?)?;
+
+    Some((
+        name_start..name_start.checked_add(name_size)?,
+        desc_start..desc_start.checked_add(desc_size)?,
+        next,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "alloc")]
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    emboss_macros::emboss!(key = "runtime", value = "first", stored_in = "emboss_meta");
+
+    emboss_macros::emboss_many!(
+        items = [
+            { key = "runtime", value = "second" },
+            { key = "build", value = 42 },
+        ],
+        stored_in = "emboss_meta"
+    );
+
+    emboss_macros::emboss!(key = "loaded", value = "noted", as_note = true);
+
+    #[test]
+    fn runtime_section_name() {
+        assert_eq!(crate::RUNTIME_SECTION_NAME, "emboss_meta");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn all_blocks_in_section() {
+        let mut values = all_blocks()
+            .map(|block| block.unwrap())
+            .flat_map(|block| block.iter().map(|pair| pair.unwrap()))
+            .filter(|(key, _)| *key == "runtime")
+            .map(|(_, value)| value)
+            .collect::<Vec<_>>();
+        values.sort();
+        assert_eq!(values, ["first", "second"]);
+    }
+
+    #[test]
+    fn blocks_after_anchor() {
+        let options = ExtractOptions::default();
+        assert_eq!(padded_blocks_with_options(b"", &options).count(), 0);
+        assert_eq!(padded_blocks_with_options(b"\0", &options).count(), 0);
+
+        let data = b"\0\x55\xB0\x77\x1A\x01foo\0one\0\0";
+        let mut blocks = padded_blocks_with_options(data, &options);
+        let block = blocks.next().unwrap().unwrap();
+        assert_eq!(block.offset(), 1);
+        assert_eq!(block.iter().next(), Some(Ok(("foo", "one"))));
+        assert!(blocks.next().is_none());
+    }
+
+    #[cfg(all(target_os = "linux", feature = "std"))]
+    #[test]
+    fn loaded_modules_include_main_program() {
+        let modules = loaded_modules();
+        let exe = std::env::current_exe().unwrap();
+        let module = modules.iter().find(|module| module.path == exe).unwrap();
+        assert_ne!(module.address, 0);
+
+        // Only blocks embossed as notes are found
+        let pairs = module
+            .blocks()
+            .flat_map(|block| block.unwrap().iter().flatten())
+            .collect::<Vec<_>>();
+        assert!(pairs.contains(&("loaded", "noted")));
+        assert!(!pairs.contains(&("runtime", "first")));
+    }
+
+    #[cfg(all(target_os = "linux", feature = "std"))]
+    #[test]
+    fn find_notes_in_segment() {
+        let note = |name: &[u8], kind: u32, desc: &[u8]| {
+            let mut note = Vec::new();
+            note.extend_from_slice(&(name.len() as u32).to_ne_bytes());
+            note.extend_from_slice(&(desc.len() as u32).to_ne_bytes());
+            note.extend_from_slice(&kind.to_ne_bytes());
+            note.extend_from_slice(name);
+            note.resize(note.len().next_multiple_of(4), 0);
+            note.extend_from_slice(desc);
+            note.resize(note.len().next_multiple_of(4), 0);
+            note
+        };
+
+        let block = crate::encode::BlockBuilder::new()
+            .str("foo", "one")
+            .build()
+            .unwrap();
+
+        // Notes of other owners or types are skipped, as is a note cut short
+        let segment = [
+            note(b"GNU\0", 3, b"build-id"),
+            note(b"Emboss\0", NOTE_TYPE, &block),
+            note(b"Emboss\0", 2, &block),
+            note(b"Emboss\0", NOTE_TYPE, &block),
+        ]
+        .concat();
+        let mut data = Vec::new();
+        find_notes(&segment, 4, &ExtractOptions::default(), &mut data);
+        assert_eq!(data, [block.clone(), block.clone()].concat());
+
+        data.clear();
+        find_notes(
+            &segment[..segment.len() - 4],
+            4,
+            &ExtractOptions::default(),
+            &mut data,
+        );
+        assert_eq!(data, block);
+
+        // As is everything after a note whose sizes run past the end of memory
+        let mut segment = note(b"Emboss\0", NOTE_TYPE, &block);
+        segment.extend_from_slice(&[0xFF; 8]);
+        segment.extend_from_slice(&NOTE_TYPE.to_ne_bytes());
+        segment.extend_from_slice(&note(b"Emboss\0", NOTE_TYPE, &block));
+        data.clear();
+        find_notes(&segment, 4, &ExtractOptions::default(), &mut data);
+        assert_eq!(data, block);
+        assert_eq!(note_layout(usize::MAX - 11, 0, 0, 4), None);
+        assert_eq!(note_layout(0, usize::MAX, 0, 8), None);
+    }
+}
@@ -0,0 +1,115 @@
+use core::fmt;
+
+use emboss_common::ValueKind;
+
+use crate::error::{EmbossError, ErrorKind};
+
+/// A typed embossed value
+///
+/// Values are embossed as their canonical text alongside a [`ValueKind`] tag, which is
+/// what this is parsed from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EmbossValue<'a> {
+    Str(&'a str),
+    U64(u64),
+    I64(i64),
+    Bool(bool),
+    /// Seconds since the Unix epoch
+    Timestamp(u64),
+    Bytes(&'a [u8]),
+}
+
+impl<'a> EmbossValue<'a> {
+    /// Parse the embossed text of a value according to its kind
+    ///
+    /// Returns `None` if the text is not a valid value of that kind.
+    pub fn parse(kind: ValueKind, text: &'a str) -> Option<Self> {
+        let value = match kind {
+            ValueKind::Str => Self::Str(text),
+            ValueKind::U64 => Self::U64(text.parse().ok()?),
+            ValueKind::I64 => Self::I64(text.parse().ok()?),
+            ValueKind::Bool => Self::Bool(text.parse().ok()?),
+            ValueKind::Timestamp => Self::Timestamp(text.parse().ok()?),
+            ValueKind::Bytes => Self::Bytes(text.as_bytes()),
+        };
+
+        Some(value)
+    }
+
+    /// Decode the raw embossed bytes of a value according to its kind
+    pub fn decode(kind: ValueKind, raw: &'a [u8]) -> Result<Self, EmbossError> {
+        if kind == ValueKind::Bytes {
+            return Ok(Self::Bytes(raw));
+        }
+
+        let text = core::str::from_utf8(raw).map_err(ErrorKind::InvalidUtf8)?;
+        Self::parse(kind, text).ok_or(ErrorKind::InvalidTypedValue(kind).into())
+    }
+
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            Self::Str(_) => ValueKind::Str,
+            Self::U64(_) => ValueKind::U64,
+            Self::I64(_) => ValueKind::I64,
+            Self::Bool(_) => ValueKind::Bool,
+            Self::Timestamp(_) => ValueKind::Timestamp,
+            Self::Bytes(_) => ValueKind::Bytes,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self {
+            Self::Str(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Self::U64(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::I64(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_timestamp(&self) -> Option<u64> {
+        match self {
+            Self::Timestamp(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            Self::Bytes(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Formats the value as the text it was embossed as, or as lowercase hex for bytes
+impl fmt::Display for EmbossValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Str(value) => value.fmt(f),
+            Self::U64(value) => value.fmt(f),
+            Self::I64(value) => value.fmt(f),
+            Self::Bool(value) => value.fmt(f),
+            Self::Timestamp(value) => value.fmt(f),
+            Self::Bytes(value) => value.iter().try_for_each(|byte| write!(f, "{:02x}", byte)),
+        }
+    }
+}
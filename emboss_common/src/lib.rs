@@ -1,12 +1,197 @@
+#![cfg_attr(not(feature = "macros"), no_std)]
+
+#[cfg(feature = "macros")]
 use serde::Deserialize;
 
 pub const LEADING_MAGIC_BYTES: u32 = 0x1A77B055;
 
+/// Whether a block can start with this magic number
+///
+/// Blocks are stored little-endian and zero bytes between them are skipped as padding, so
+/// the lowest byte of the magic number can't be zero.
+pub const fn is_valid_magic(magic: u32) -> bool {
+    magic & 0xFF != 0
+}
+
+/// Written in place of the v1 count byte to mark a versioned header
+///
+/// A v1 block never has a count of zero, so the two layouts can't be confused.
+pub const VERSIONED_HEADER_MARKER: u8 = 0;
+
+/// The version of the binary format emitted by the emboss macros
+pub const FORMAT_VERSION: u8 = 2;
+
+/// Set in the flags of a v2 header when a CRC-32 of the block follows the count
+pub const FLAG_CHECKSUM: u8 = 1 << 0;
+
+/// Set in the flags of a v2 header when an Ed25519 signature of the block follows the count
+/// and checksum
+pub const FLAG_SIGNATURE: u8 = 1 << 1;
+
+/// The length of an Ed25519 signature, in bytes
+pub const SIGNATURE_LEN: usize = 64;
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+
+        table[index] = crc;
+        index += 1;
+    }
+
+    table
+};
+
+/// An incremental CRC-32 (IEEE), usable at compile time
+///
+/// The checksum of a block covers its `COUNT` followed by every entry, in order.
+#[derive(Clone, Copy, Debug)]
+pub struct Crc32(u32);
+
+impl Crc32 {
+    pub const fn new() -> Self {
+        Self(0xFFFF_FFFF)
+    }
+
+    pub const fn update(self, bytes: &[u8]) -> Self {
+        let mut crc = self.0;
+        let mut index = 0;
+        while index < bytes.len() {
+            crc = CRC32_TABLE[((crc ^ bytes[index] as u32) & 0xFF) as usize] ^ (crc >> 8);
+            index += 1;
+        }
+
+        Self(crc)
+    }
+
+    pub const fn finish(self) -> u32 {
+        !self.0
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The type of an embossed value, stored as a tag ahead of each v2 entry
+///
+/// Typed values are still stored as their canonical text, so every value other than bytes
+/// can be read as a string regardless of its kind.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ValueKind {
+    Str = 0,
+    U64 = 1,
+    I64 = 2,
+    Bool = 3,
+    /// Seconds since the Unix epoch
+    Timestamp = 4,
+    Bytes = 5,
+}
+
+impl ValueKind {
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        let kind = match tag {
+            0 => Self::Str,
+            1 => Self::U64,
+            2 => Self::I64,
+            3 => Self::Bool,
+            4 => Self::Timestamp,
+            5 => Self::Bytes,
+            _ => return None,
+        };
+
+        Some(kind)
+    }
+}
+
+/// How a value is encoded, stored in the high bits of the kind tag ahead of each v2 entry
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "macros", derive(Deserialize))]
+#[cfg_attr(feature = "macros", serde(try_from = "String"))]
+pub enum Codec {
+    #[default]
+    None = 0,
+    Deflate = 1,
+}
+
+impl Codec {
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        let codec = match tag {
+            0 => Self::None,
+            1 => Self::Deflate,
+            _ => return None,
+        };
+
+        Some(codec)
+    }
+}
+
+#[cfg(feature = "macros")]
+impl TryFrom<String> for Codec {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        match name.as_str() {
+            "deflate" => Ok(Self::Deflate),
+            _ => Err(format!("Unknown codec '{}', expected \"deflate\"", name)),
+        }
+    }
+}
+
+/// The number of bits the codec is shifted by within an entry's kind tag
+pub const CODEC_SHIFT: u8 = 4;
+
+/// Combine the kind and codec of an entry into the tag stored ahead of it
+pub const fn entry_tag(kind: ValueKind, codec: Codec) -> u8 {
+    kind as u8 | (codec as u8) << CODEC_SHIFT
+}
+
 #[cfg(target_os = "macos")]
 pub const DEFAULT_SEGMENT_NAME: &str = "__DATA";
 
 pub const DEFAULT_SECTION_NAME: &str = ".emboss.meta";
 
+/// The section walked by `emboss::runtime` to find every block linked into a process
+///
+/// It is named as a C identifier so that ELF linkers define `__start_emboss_meta` and
+/// `__stop_emboss_meta` around it, which `.emboss.meta` can't be.
+pub const RUNTIME_SECTION_NAME: &str = "emboss_meta";
+
+/// The section blocks embossed with `as_note` are stored in by default
+pub const NOTE_SECTION_NAME: &str = ".note.emboss";
+
+/// The owner of the ELF notes holding embossed blocks
+pub const NOTE_NAME: &str = "Emboss";
+
+/// The type of the ELF notes holding embossed blocks, whose descriptor is a single block
+pub const NOTE_TYPE: u32 = 1;
+
+/// The section of the package metadata note described by
+/// <https://systemd.io/ELF_PACKAGE_METADATA/>
+pub const PACKAGE_NOTE_SECTION_NAME: &str = ".note.package";
+
+/// The owner of the package metadata note
+pub const PACKAGE_NOTE_NAME: &str = "FDO";
+
+/// The type of the package metadata note, whose descriptor is a NUL-terminated JSON object
+pub const PACKAGE_NOTE_TYPE: u32 = 0xcafe_1a7e;
+
+#[cfg(feature = "macros")]
 #[derive(Deserialize, Clone, Debug)]
 pub struct EmbossingOptions {
     #[serde(default = "get_default_section_name")]
@@ -14,27 +199,98 @@ pub struct EmbossingOptions {
 
     pub export_name: Option<String>,
 
+    #[serde(default = "get_default_checksum")]
+    pub checksum: bool,
+
+    /// The environment variable holding the hex-encoded Ed25519 secret key to sign with
+    pub sign_with_env: Option<String>,
+
+    /// Compress values with this codec when it makes them smaller
+    pub compress: Option<Codec>,
+
+    /// The magic number each block starts with, so a project's blocks can be told apart
+    #[serde(default = "get_default_magic")]
+    pub magic: u32,
+
+    /// Wrap the block in an ELF note, found through the program headers even once the
+    /// section headers are stripped
+    #[serde(default)]
+    pub as_note: bool,
+
     #[cfg(target_os = "macos")]
     #[serde(default = "get_default_segment_name")]
     pub segment: String,
 }
 
+#[cfg(feature = "macros")]
 impl Default for EmbossingOptions {
     fn default() -> Self {
         Self {
             stored_in: DEFAULT_SECTION_NAME.to_string(),
             export_name: None,
+            checksum: true,
+            sign_with_env: None,
+            compress: None,
+            magic: LEADING_MAGIC_BYTES,
+            as_note: false,
             #[cfg(target_os = "macos")]
             segment: DEFAULT_SEGMENT_NAME.to_string(),
         }
     }
 }
 
-#[cfg(target_os = "macos")]
+#[cfg(all(feature = "macros", target_os = "macos"))]
 fn get_default_segment_name() -> String {
     DEFAULT_SEGMENT_NAME.to_string()
 }
 
+#[cfg(feature = "macros")]
 fn get_default_section_name() -> String {
     DEFAULT_SECTION_NAME.to_string()
 }
+
+#[cfg(feature = "macros")]
+fn get_default_checksum() -> bool {
+    true
+}
+
+#[cfg(feature = "macros")]
+fn get_default_magic() -> u32 {
+    LEADING_MAGIC_BYTES
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(Crc32::new().update(b"123456789").finish(), 0xCBF4_3926);
+        assert_eq!(
+            Crc32::new().update(b"1234").update(b"56789").finish(),
+            0xCBF4_3926
+        );
+    }
+
+    #[test]
+    fn magic_validity() {
+        assert!(is_valid_magic(LEADING_MAGIC_BYTES));
+        assert!(is_valid_magic(0x0000_00FF));
+        assert!(!is_valid_magic(0xC0DE_0100));
+    }
+
+    #[test]
+    fn entry_tag_holds_kind_and_codec() {
+        let tag = entry_tag(ValueKind::Bytes, Codec::Deflate);
+        assert_eq!(tag, 0x15);
+        assert_eq!(ValueKind::from_tag(tag & 0x0F), Some(ValueKind::Bytes));
+        assert_eq!(Codec::from_tag(tag >> CODEC_SHIFT), Some(Codec::Deflate));
+    }
+
+    #[cfg(feature = "macros")]
+    #[test]
+    fn codec_from_name() {
+        assert_eq!(Codec::try_from("deflate".to_string()), Ok(Codec::Deflate));
+        assert!(Codec::try_from("zip".to_string()).is_err());
+    }
+}
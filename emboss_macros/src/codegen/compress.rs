@@ -0,0 +1,69 @@
+use emboss_common::{Codec, EmbossingOptions};
+
+use crate::macro_impl::Embossable;
+
+/// Compress the value of each item with the codec chosen by `compress`
+///
+/// Values are only compressed when that makes them smaller, so each item records the codec
+/// that was actually used.
+pub(crate) fn apply(
+    items: Vec<Embossable>,
+    options: &EmbossingOptions,
+) -> Result<Vec<Embossable>, String> {
+    let Some(codec) = options.compress else {
+        return Ok(items);
+    };
+
+    // The exported accessors borrow values straight out of the embossed data
+    if options.export_name.is_some() {
+        return Err(
+            "Compressed values can't be exported, remove either 'compress' or 'export_name'."
+                .to_string(),
+        );
+    }
+
+    items
+        .into_iter()
+        .map(|item| compress(item, codec))
+        .collect()
+}
+
+#[cfg(feature = "deflate")]
+fn compress(mut item: Embossable, codec: Codec) -> Result<Embossable, String> {
+    use proc_macro2::Span;
+    use quote::quote;
+    use syn::LitByteStr;
+
+    let value = item.known_value.as_deref().ok_or_else(|| {
+        format!(
+            "The value of '{}' is only known after expansion, so it can't be compressed.",
+            item.key
+        )
+    })?;
+
+    let compressed = match codec {
+        Codec::None => return Ok(item),
+        Codec::Deflate => miniz_oxide::deflate::compress_to_vec(value, 10),
+    };
+
+    if compressed.len() >= value.len() {
+        return Ok(item);
+    }
+
+    // The original expression is still evaluated so that `env!` keeps tracking its variable
+    let original = &item.value;
+    let literal = LitByteStr::new(&compressed, Span::call_site());
+    item.value = quote! {{
+        let _ = #original;
+        #literal
+    }};
+    item.codec = codec;
+    item.known_value = Some(compressed);
+
+    Ok(item)
+}
+
+#[cfg(not(feature = "deflate"))]
+fn compress(_: Embossable, _: Codec) -> Result<Embossable, String> {
+    Err("Compressing embossed values requires the 'deflate' feature of emboss.".to_string())
+}
@@ -1,4 +1,4 @@
-use emboss_common::EmbossingOptions;
+use emboss_common::{Codec, EmbossingOptions, ValueKind};
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 
@@ -8,21 +8,44 @@ pub(crate) fn emit(items: &[Embossable], _: &EmbossingOptions) -> TokenStream2 {
     let items_consts_expr = items
         .iter()
         .enumerate()
-        .map(|(index, Embossable { key, value, .. })| {
-            let key_name = ident::key_const(index);
-            let key_size_name = ident::key_size_const(index);
+        .map(
+            |(
+                index,
+                Embossable {
+                    key,
+                    value,
+                    kind,
+                    codec,
+                    ..
+                },
+            )| {
+                let key_name = ident::key_const(index);
+                let key_size_name = ident::key_size_const(index);
 
-            let value_name = ident::value_const(index);
-            let value_size_name = ident::value_size_const(index);
+                let value_name = ident::value_const(index);
+                let value_size_name = ident::value_size_const(index);
 
-            quote! {
-                const #key_name: &str = #key;
-                const #value_name: &str = #value;
+                // Compressed values are always bytes, whatever they decompress to
+                let value_decl = if *kind == ValueKind::Bytes || *codec != Codec::None {
+                    quote! {
+                        const #value_name: &[u8] = #value;
+                        const #value_size_name: usize = #value_name.len();
+                    }
+                } else {
+                    quote! {
+                        const #value_name: &str = #value;
+                        const #value_size_name: usize = #value_name.as_bytes().len();
+                    }
+                };
 
-                const #key_size_name: usize = #key_name.as_bytes().len();
-                const #value_size_name: usize = #value_name.as_bytes().len();
-            }
-        })
+                quote! {
+                    const #key_name: &str = #key;
+                    const #key_size_name: usize = #key_name.as_bytes().len();
+
+                    #value_decl
+                }
+            },
+        )
         .collect::<Vec<_>>();
 
     quote! {
@@ -1,8 +1,11 @@
-use emboss_common::EmbossingOptions;
+use emboss_common::{EmbossingOptions, ValueKind};
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 
-use crate::{codegen::ident, macro_impl::Embossable};
+use crate::{
+    codegen::{emboss_crate, ident},
+    macro_impl::Embossable,
+};
 
 pub(crate) fn emit(items: &[Embossable], _: &EmbossingOptions) -> TokenStream2 {
     let enum_variant_expr = items
@@ -31,6 +34,10 @@ pub(crate) fn emit(items: &[Embossable], _: &EmbossingOptions) -> TokenStream2 {
         quote! {}
     };
 
+    // Byte values needn't be UTF-8, so the string accessors leave them out, and they're
+    // only handed out by `get_bytes` and the other typed accessors
+    let has_bytes = items.iter().any(|item| item.kind == ValueKind::Bytes);
+
     let items_match_enum_expr = items
         .iter()
         .enumerate()
@@ -38,39 +45,57 @@ pub(crate) fn emit(items: &[Embossable], _: &EmbossingOptions) -> TokenStream2 {
             |(
                 index,
                 Embossable {
-                    key, variant_name, ..
+                    key,
+                    kind,
+                    variant_name,
+                    ..
                 },
             )| {
-                let enum_variant_name = ident::enum_variant(key, variant_name.as_ref())
-                    .map(|variant_name| quote! { EmbossedKeyKind::#variant_name })?;
-                let branch_body = get_branch_impl(index);
+                let enum_variant_name = ident::enum_variant(key, variant_name.as_ref())?;
+                let branch_body = match (kind, has_bytes) {
+                    (ValueKind::Bytes, _) => quote! { None },
+                    (_, true) => {
+                        let branch_body = get_branch_impl(index);
+                        quote! { Some(#branch_body) }
+                    }
+                    (_, false) => get_branch_impl(index),
+                };
 
                 Some(quote! {
-                    #enum_variant_name => #branch_body,
+                    EmbossedKeyKind::#enum_variant_name => #branch_body,
                 })
             },
         )
         .collect::<Vec<_>>();
 
-    let get_enum_fn = if !items_match_enum_expr.is_empty() {
-        quote! {
+    // Blocks holding byte values need a way to say a value isn't a string, so their
+    // `get_by_kind` returns an `Option`, while every other block keeps returning the pair
+    let get_enum_fn = match (items_match_enum_expr.is_empty(), has_bytes) {
+        (true, _) => quote! {},
+        (false, true) => quote! {
+            pub fn get_by_kind(&self, kind: EmbossedKeyKind) -> Option<(&str, &str)> {
+                match kind {
+                    #(#items_match_enum_expr)*
+                }
+            }
+        },
+        (false, false) => quote! {
             pub fn get_by_kind(&self, kind: EmbossedKeyKind) -> (&str, &str) {
                 match kind {
                     #(#items_match_enum_expr)*
                 }
             }
-        }
-    } else {
-        quote! {}
+        },
     };
 
     let items_match_index_expr = items
         .iter()
         .enumerate()
+        .filter(|(_, item)| item.kind != ValueKind::Bytes)
         .map(|(index, _)| {
             let branch_body = get_branch_impl(index);
             quote! {
-                #index => #branch_body,
+                #index => Some(#branch_body),
             }
         })
         .collect::<Vec<_>>();
@@ -78,40 +103,155 @@ pub(crate) fn emit(items: &[Embossable], _: &EmbossingOptions) -> TokenStream2 {
     let items_match_name_expr = items
         .iter()
         .enumerate()
+        .filter(|(_, item)| item.kind != ValueKind::Bytes)
         .map(|(index, _)| {
             let key_name = ident::key_const(index);
             let branch_body = get_branch_impl(index);
 
             quote! {
-                #key_name => #branch_body,
+                #key_name => Some(#branch_body),
             }
         })
         .collect::<Vec<_>>();
 
+    // Typed values are handed out as an `EmbossValue`, so there's nothing to return them as
+    // when the macros are used without the emboss crate
+    let typed_impl = match emboss_crate() {
+        Some(emboss) => emit_typed(items, &emboss),
+        None => quote! {},
+    };
+
     quote! {
         impl Embossed {
             pub fn get_by_index(&self, index: usize) -> Option<(&str, &str)> {
-                let pair = match index {
+                match index {
                     #(#items_match_index_expr)*
+                    _ => None,
+                }
+            }
+
+            pub fn get_by_key(&self, key: &str) -> Option<(&str, &str)> {
+                match key {
+                    #(#items_match_name_expr)*
+                    _ => None,
+                }
+            }
+
+            #get_enum_fn
+        }
+
+        #typed_impl
+
+        #enum_decl
+    }
+}
+
+fn emit_typed(items: &[Embossable], emboss: &TokenStream2) -> TokenStream2 {
+    let items_typed_match_enum_expr = items
+        .iter()
+        .enumerate()
+        .filter_map(
+            |(
+                index,
+                Embossable {
+                    key,
+                    kind,
+                    variant_name,
+                    ..
+                },
+            )| {
+                let enum_variant_name = ident::enum_variant(key, variant_name.as_ref())?;
+                let branch_body = get_typed_branch_impl(index, *kind, emboss);
+
+                Some(quote! {
+                    EmbossedKeyKind::#enum_variant_name => #branch_body,
+                })
+            },
+        )
+        .collect::<Vec<_>>();
+
+    let get_typed_enum_fn = if !items_typed_match_enum_expr.is_empty() {
+        quote! {
+            pub fn get_typed_by_kind(&self, kind: EmbossedKeyKind) -> (&str, #emboss::EmbossValue<'_>) {
+                match kind {
+                    #(#items_typed_match_enum_expr)*
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let items_typed_match_index_expr = items
+        .iter()
+        .enumerate()
+        .map(|(index, Embossable { kind, .. })| {
+            let branch_body = get_typed_branch_impl(index, *kind, emboss);
+            quote! {
+                #index => #branch_body,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let items_typed_match_name_expr = items
+        .iter()
+        .enumerate()
+        .map(|(index, Embossable { kind, .. })| {
+            let key_name = ident::key_const(index);
+            let branch_body = get_typed_branch_impl(index, *kind, emboss);
+
+            quote! {
+                #key_name => #branch_body,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    quote! {
+        impl Embossed {
+            pub fn get_typed_by_index(&self, index: usize) -> Option<(&str, #emboss::EmbossValue<'_>)> {
+                let pair = match index {
+                    #(#items_typed_match_index_expr)*
                     _ => return None,
                 };
 
                 Some(pair)
             }
 
-            pub fn get_by_key(&self, key: &str) -> Option<(&str, &str)> {
+            pub fn get_typed_by_key(&self, key: &str) -> Option<(&str, #emboss::EmbossValue<'_>)> {
                 let pair = match key {
-                    #(#items_match_name_expr)*
+                    #(#items_typed_match_name_expr)*
                     _ => return None,
                 };
 
                 Some(pair)
             }
 
-            #get_enum_fn
-        }
+            pub fn get_str(&self, key: &str) -> Option<&str> {
+                self.get_typed_by_key(key)?.1.as_str()
+            }
 
-        #enum_decl
+            pub fn get_u64(&self, key: &str) -> Option<u64> {
+                self.get_typed_by_key(key)?.1.as_u64()
+            }
+
+            pub fn get_i64(&self, key: &str) -> Option<i64> {
+                self.get_typed_by_key(key)?.1.as_i64()
+            }
+
+            pub fn get_bool(&self, key: &str) -> Option<bool> {
+                self.get_typed_by_key(key)?.1.as_bool()
+            }
+
+            pub fn get_timestamp(&self, key: &str) -> Option<u64> {
+                self.get_typed_by_key(key)?.1.as_timestamp()
+            }
+
+            pub fn get_bytes(&self, key: &str) -> Option<&[u8]> {
+                self.get_typed_by_key(key)?.1.as_bytes()
+            }
+
+            #get_typed_enum_fn
+        }
     }
 }
 
@@ -119,15 +259,65 @@ fn get_branch_impl(index: usize) -> TokenStream2 {
     let key_var_name = ident::key_field(index);
     let value_var_name = ident::value_field(index);
 
+    // Every value but bytes, which are left out, was checked to be UTF-8 while expanding
     quote! {
-        unsafe {
-            let key = core::str::from_utf8_unchecked(
-                &self.#key_var_name[..self.#key_var_name.len() - 1]
-            );
-            let value = core::str::from_utf8_unchecked(
-                &self.#value_var_name[..self.#value_var_name.len() - 1]
-            );
+        {
+            let key = unsafe { core::str::from_utf8_unchecked(&self.#key_var_name) };
+            let value = unsafe { core::str::from_utf8_unchecked(&self.#value_var_name) };
             (key, value)
         }
     }
 }
+
+fn get_typed_branch_impl(index: usize, kind: ValueKind, emboss: &TokenStream2) -> TokenStream2 {
+    let key_var_name = ident::key_field(index);
+    let value_var_name = ident::value_field(index);
+    let value_name = ident::value_const(index);
+
+    // Typed values are parsed back out of their embossed text while compiling, so the
+    // accessors never have to handle a parse failure
+    let value = match kind {
+        ValueKind::Str => quote! {
+            #emboss::EmbossValue::Str(unsafe {
+                core::str::from_utf8_unchecked(&self.#value_var_name)
+            })
+        },
+        ValueKind::U64 => quote! {
+            #emboss::EmbossValue::U64(const {
+                match u64::from_str_radix(#value_name, 10) {
+                    Ok(value) => value,
+                    Err(_) => panic!("embossed value is not a valid u64"),
+                }
+            })
+        },
+        ValueKind::I64 => quote! {
+            #emboss::EmbossValue::I64(const {
+                match i64::from_str_radix(#value_name, 10) {
+                    Ok(value) => value,
+                    Err(_) => panic!("embossed value is not a valid i64"),
+                }
+            })
+        },
+        ValueKind::Bool => quote! {
+            #emboss::EmbossValue::Bool(const { matches!(#value_name.as_bytes(), b"true") })
+        },
+        ValueKind::Timestamp => quote! {
+            #emboss::EmbossValue::Timestamp(const {
+                match u64::from_str_radix(#value_name, 10) {
+                    Ok(value) => value,
+                    Err(_) => panic!("embossed value is not a valid timestamp"),
+                }
+            })
+        },
+        ValueKind::Bytes => quote! {
+            #emboss::EmbossValue::Bytes(&self.#value_var_name)
+        },
+    };
+
+    quote! {
+        {
+            let key = unsafe { core::str::from_utf8_unchecked(&self.#key_var_name) };
+            (key, #value)
+        }
+    }
+}
@@ -27,6 +27,18 @@ pub(crate) fn value_field(index: usize) -> Ident {
     Ident::new(&format!("value_{}", index), Span::call_site())
 }
 
+pub(crate) fn kind_field(index: usize) -> Ident {
+    Ident::new(&format!("kind_{}", index), Span::call_site())
+}
+
+pub(crate) fn key_len_field(index: usize) -> Ident {
+    Ident::new(&format!("key_{}_len", index), Span::call_site())
+}
+
+pub(crate) fn value_len_field(index: usize) -> Ident {
+    Ident::new(&format!("value_{}_len", index), Span::call_site())
+}
+
 pub(crate) fn enum_variant(key: &String, variant_name: Option<&String>) -> Option<Ident> {
     variant_name
         .map(Cow::from)
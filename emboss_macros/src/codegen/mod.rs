@@ -1,25 +1,76 @@
+mod compress;
 mod const_decl;
 mod exported;
 mod ident;
+mod package_note;
 mod packed;
+mod signature;
 mod static_value;
 
-use emboss_common::EmbossingOptions;
+use emboss_common::{EmbossingOptions, entry_tag};
+use proc_macro_crate::FoundCrate;
 use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
 use quote::quote;
 
 use crate::macro_impl::Embossable;
 
+/// The path to the `emboss` crate from the code being expanded, if it depends on it
+///
+/// Follows any rename in its `Cargo.toml`. Within `emboss` itself this is still `::emboss`,
+/// which resolves through its `extern crate self as emboss`, as its doctests need it to.
+pub(crate) fn emboss_crate() -> Option<TokenStream2> {
+    match proc_macro_crate::crate_name("emboss").ok()? {
+        FoundCrate::Itself => Some(quote! { ::emboss }),
+        FoundCrate::Name(name) => {
+            let name = Ident::new(&name, Span::call_site());
+            Some(quote! { ::#name })
+        }
+    }
+}
+
+/// The bytes covered by the checksum and signature of a block: `COUNT` followed by every
+/// entry, exactly as they're laid out in the embossed block
+///
+/// Fails with the first item whose value is only known after expansion.
+pub(crate) fn covered_bytes(items: &[Embossable]) -> Result<Vec<u8>, &Embossable> {
+    let mut bytes = (items.len() as u32).to_le_bytes().to_vec();
+    for item in items {
+        let value = item.known_value.as_deref().ok_or(item)?;
+
+        bytes.push(entry_tag(item.kind, item.codec));
+        bytes.extend_from_slice(&(item.key.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(item.key.as_bytes());
+        bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(value);
+    }
+
+    Ok(bytes)
+}
+
 pub(crate) fn emboss_token(item: Embossable, options: EmbossingOptions) -> TokenStream2 {
     emboss_token_multiple(vec![item], options)
 }
 
+pub(crate) fn package_note_token(pairs: &[(String, String)]) -> TokenStream2 {
+    package_note::emit(pairs)
+}
+
 pub(crate) fn emboss_token_multiple(
     items: Vec<Embossable>,
     options: EmbossingOptions,
 ) -> TokenStream2 {
+    let items = match compress::apply(items, &options) {
+        Ok(items) => items,
+        Err(error_msg) => {
+            return quote! {
+                const _: () = compile_error!(#error_msg);
+            };
+        }
+    };
+
     let const_decl = const_decl::emit(&items, &options);
     let packed_struct = packed::emit(&items, &options);
+    let signature_decl = signature::emit(&items, &options);
     let static_init = static_value::emit(&items, &options);
 
     let export_name = &options.export_name;
@@ -40,6 +91,8 @@ pub(crate) fn emboss_token_multiple(
         #top_level_fragment {
             #const_decl
 
+            #signature_decl
+
             #packed_struct
 
             #public_api
@@ -0,0 +1,80 @@
+use std::fmt::Write;
+
+use emboss_common::{PACKAGE_NOTE_NAME, PACKAGE_NOTE_SECTION_NAME, PACKAGE_NOTE_TYPE};
+use proc_macro2::{Literal, TokenStream as TokenStream2};
+use quote::quote;
+
+pub(crate) fn emit(pairs: &[(String, String)]) -> TokenStream2 {
+    if cfg!(target_os = "macos") {
+        return quote! {
+            const _: () = compile_error!("ELF notes can't be embossed on macOS.");
+        };
+    }
+
+    let mut json = String::from("{");
+    for (index, (key, value)) in pairs.iter().enumerate() {
+        if index > 0 {
+            json.push(',');
+        }
+
+        write_json_string(&mut json, key);
+        json.push(':');
+        write_json_string(&mut json, value);
+    }
+    json.push('}');
+
+    // Both the name and the descriptor are NUL-terminated, and padded to a multiple of four
+    // bytes
+    let name_size = PACKAGE_NOTE_NAME.len() + 1;
+    let mut name = PACKAGE_NOTE_NAME.as_bytes().to_vec();
+    name.resize(name_size.next_multiple_of(4), 0);
+
+    let desc_size = json.len() + 1;
+    let mut desc = json.into_bytes();
+    desc.resize(desc_size.next_multiple_of(4), 0);
+
+    let (name_len, desc_len) = (name.len(), desc.len());
+    let (name_size, desc_size) = (name_size as u32, desc_size as u32);
+    let (name, desc) = (Literal::byte_string(&name), Literal::byte_string(&desc));
+
+    quote! {
+        const _: () = {
+            #[repr(C, align(4))]
+            struct PackageNote {
+                name_size: u32,
+                desc_size: u32,
+                kind: u32,
+                name: [u8; #name_len],
+                desc: [u8; #desc_len],
+            }
+
+            #[used]
+            #[unsafe(link_section = #PACKAGE_NOTE_SECTION_NAME)]
+            static PACKAGE_NOTE: PackageNote = PackageNote {
+                name_size: #name_size,
+                desc_size: #desc_size,
+                kind: #PACKAGE_NOTE_TYPE,
+                name: *#name,
+                desc: *#desc,
+            };
+        };
+    }
+}
+
+fn write_json_string(json: &mut String, value: &str) {
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c < ' ' => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
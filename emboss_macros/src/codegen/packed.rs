@@ -1,10 +1,10 @@
-use emboss_common::EmbossingOptions;
-use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use emboss_common::{EmbossingOptions, SIGNATURE_LEN};
+use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 
 use crate::{codegen::ident, macro_impl::Embossable};
 
-pub(crate) fn emit(items: &[Embossable], _: &EmbossingOptions) -> TokenStream2 {
+pub(crate) fn emit(items: &[Embossable], options: &EmbossingOptions) -> TokenStream2 {
     let fields = items
         .iter()
         .enumerate()
@@ -12,21 +12,65 @@ pub(crate) fn emit(items: &[Embossable], _: &EmbossingOptions) -> TokenStream2 {
             let key_size_name = ident::key_size_const(index);
             let value_size_name = ident::value_size_const(index);
 
-            let key_name = Ident::new(&format!("key_{}", index), Span::call_site());
-            let value_name = Ident::new(&format!("value_{}", index), Span::call_site());
+            let kind_name = ident::kind_field(index);
+            let key_name = ident::key_field(index);
+            let key_len_name = ident::key_len_field(index);
+            let value_name = ident::value_field(index);
+            let value_len_name = ident::value_len_field(index);
 
             quote! {
-                #key_name: [u8; #key_size_name + 1],
-                #value_name: [u8; #value_size_name + 1],
+                #kind_name: u8,
+                #key_len_name: [u8; 4],
+                #key_name: [u8; #key_size_name],
+                #value_len_name: [u8; 4],
+                #value_name: [u8; #value_size_name],
             }
         })
         .collect::<Vec<_>>();
 
+    let checksum_field = if options.checksum {
+        quote! { checksum: [u8; 4], }
+    } else {
+        quote! {}
+    };
+
+    let signature_field = if options.sign_with_env.is_some() {
+        quote! { signature: [u8; #SIGNATURE_LEN], }
+    } else {
+        quote! {}
+    };
+
+    // Note headers are made of 4-byte words, and the descriptor is padded to the next one
+    let note_struct = if options.as_note {
+        quote! {
+            const NOTE_PADDING: usize = (4 - ::core::mem::size_of::<Embossed>() % 4) % 4;
+
+            #[repr(C, align(4))]
+            pub struct EmbossedNote {
+                name_size: u32,
+                desc_size: u32,
+                kind: u32,
+                name: [u8; 8],
+                desc: Embossed,
+                padding: [u8; NOTE_PADDING],
+            }
+        }
+    } else {
+        quote! {}
+    };
+
     quote! {
+        #note_struct
+
         #[repr(C, packed)]
         pub struct Embossed {
-            leading: u32,
-            field_count: u8,
+            leading: [u8; 4],
+            marker: u8,
+            version: u8,
+            flags: u8,
+            field_count: [u8; 4],
+            #checksum_field
+            #signature_field
             #(#fields)*
         }
     }
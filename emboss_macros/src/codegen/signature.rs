@@ -0,0 +1,78 @@
+use emboss_common::{EmbossingOptions, SIGNATURE_LEN};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+use crate::macro_impl::Embossable;
+
+pub(crate) fn emit(items: &[Embossable], options: &EmbossingOptions) -> TokenStream2 {
+    let Some(env_var) = &options.sign_with_env else {
+        return quote! {};
+    };
+
+    // The macro reads the key itself, which cargo doesn't see. Naming the variable through
+    // `option_env!` makes it track the key, so a rotated key gets the block signed again.
+    let track = quote! {
+        const _: Option<&str> = option_env!(#env_var);
+    };
+
+    match sign(items, env_var) {
+        Ok(signature) => quote! {
+            #track
+            const SIGNATURE: [u8; #SIGNATURE_LEN] = [#(#signature),*];
+        },
+        // Still declare the signature so the failure is the only error reported
+        Err(error_msg) => quote! {
+            #track
+            const SIGNATURE: [u8; #SIGNATURE_LEN] = [0; #SIGNATURE_LEN];
+            const _: () = compile_error!(#error_msg);
+        },
+    }
+}
+
+/// Sign the block with the key held in `env_var`
+///
+/// The signature covers the same bytes as the checksum.
+#[cfg(feature = "signatures")]
+fn sign(items: &[Embossable], env_var: &str) -> Result<[u8; SIGNATURE_LEN], String> {
+    use ed25519_dalek::{Signer, SigningKey};
+
+    use crate::codegen::covered_bytes;
+
+    let key = std::env::var(env_var)
+        .map_err(|_| format!("The signing key variable '{}' is not set.", env_var))?;
+    let seed = parse_hex_key(key.trim()).ok_or_else(|| {
+        format!(
+            "The signing key in '{}' should be a hex-encoded, 32 byte Ed25519 secret key.",
+            env_var
+        )
+    })?;
+
+    let message = covered_bytes(items).map_err(|item| {
+        format!(
+            "The value of '{}' is only known after expansion, so it can't be signed.",
+            item.key
+        )
+    })?;
+
+    Ok(SigningKey::from_bytes(&seed).sign(&message).to_bytes())
+}
+
+#[cfg(not(feature = "signatures"))]
+fn sign(_: &[Embossable], _: &str) -> Result<[u8; SIGNATURE_LEN], String> {
+    Err("Signing embossed data requires the 'signatures' feature of emboss.".to_string())
+}
+
+#[cfg(feature = "signatures")]
+fn parse_hex_key(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 {
+        return None;
+    }
+
+    let mut key = [0u8; 32];
+    for (byte, pair) in key.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        let pair = std::str::from_utf8(pair).ok()?;
+        *byte = u8::from_str_radix(pair, 16).ok()?;
+    }
+
+    Some(key)
+}
@@ -3,17 +3,28 @@ use std::{
     sync::{LazyLock, Mutex},
 };
 
-use emboss_common::{EmbossingOptions, LEADING_MAGIC_BYTES};
-use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use emboss_common::{
+    Codec, Crc32, DEFAULT_SECTION_NAME, EmbossingOptions, FLAG_CHECKSUM, FLAG_SIGNATURE,
+    FORMAT_VERSION, NOTE_NAME, NOTE_SECTION_NAME, NOTE_TYPE, RUNTIME_SECTION_NAME,
+    VERSIONED_HEADER_MARKER, ValueKind, entry_tag, is_valid_magic,
+};
+use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 
-use crate::{codegen::ident, macro_impl::Embossable};
+use crate::{
+    codegen::{covered_bytes, emboss_crate, ident},
+    macro_impl::Embossable,
+};
 
 static SECTION_SET: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
 
 pub(crate) fn emit(items: &[Embossable], options: &EmbossingOptions) -> TokenStream2 {
     let EmbossingOptions {
         stored_in,
+        checksum,
+        sign_with_env,
+        magic,
+        as_note,
         #[cfg(target_os = "macos")]
         segment,
         ..
@@ -23,19 +34,49 @@ pub(crate) fn emit(items: &[Embossable], options: &EmbossingOptions) -> TokenStr
         return quote! {
             const _: () = compile_error!("No data will be embossed.");
         };
-    } else if items.len() > u8::MAX as usize {
+    } else if !is_valid_magic(*magic) {
+        let error_msg = format!(
+            "The magic number {:#010x} can't be used, as its lowest byte is zero.",
+            magic
+        );
+        return quote! {
+            const _: () = compile_error!(#error_msg);
+        };
+    } else if items.len() > u32::MAX as usize {
         return quote! {
             const _: () = compile_error!("Too many items to emboss, consider breaking up the data into multiple segments/sections.");
         };
+    } else if *as_note && cfg!(target_os = "macos") {
+        return quote! {
+            const _: () = compile_error!("ELF notes can't be embossed on macOS.");
+        };
+    }
+
+    // Only sections named like notes are laid out as notes by the linker
+    let stored_in = match *as_note && stored_in == DEFAULT_SECTION_NAME {
+        true => NOTE_SECTION_NAME,
+        false => stored_in.as_str(),
+    };
+    if *as_note && !stored_in.starts_with(".note") {
+        let error_msg = format!(
+            "Notes can't be stored in '{}', as its name doesn't start with '.note'.",
+            stored_in
+        );
+        return quote! {
+            const _: () = compile_error!(#error_msg);
+        };
     }
 
     #[cfg(target_os = "macos")]
-    let stored_in = format!("{},{}", segment, stored_in);
+    let stored_in = &format!("{},{}", segment, stored_in);
     {
         let mut section_set = SECTION_SET
             .lock()
             .expect("should be able to acquire section set lock");
-        if section_set.contains(&stored_in) {
+        // Blocks are meant to be gathered in the runtime section, however many there are, and
+        // notes are made to be laid out one after the other
+        let shared = *as_note || options.stored_in == RUNTIME_SECTION_NAME;
+        if section_set.contains(stored_in) && !shared {
             let error_msg = format!(
                 "There is already embossed data stored in '{}'. You probably meant to call this macro with a different segment/section.",
                 stored_in
@@ -44,29 +85,38 @@ pub(crate) fn emit(items: &[Embossable], options: &EmbossingOptions) -> TokenStr
                 const _: () = compile_error!(#error_msg);
             };
         }
-        section_set.insert(stored_in.clone());
+        section_set.insert(stored_in.to_string());
     }
 
     let items_len = items.len();
     let items_init_expr = items
         .iter()
         .enumerate()
-        .map(|(index, _)| {
+        .map(|(index, Embossable { kind, codec, .. })| {
             let key_name = ident::key_const(index);
             let key_size_name = ident::key_size_const(index);
 
             let value_name = ident::value_const(index);
             let value_size_name = ident::value_size_const(index);
+            let value_bytes = if *kind == ValueKind::Bytes || *codec != Codec::None {
+                quote! { #value_name }
+            } else {
+                quote! { #value_name.as_bytes() }
+            };
 
-            let key_var_name = Ident::new(&format!("key_{}", index), Span::call_site());
-            let value_var_name = Ident::new(&format!("value_{}", index), Span::call_site());
+            let key_var_name = ident::key_field(index);
+            let key_len_var_name = ident::key_len_field(index);
+            let value_var_name = ident::value_field(index);
+            let value_len_var_name = ident::value_len_field(index);
 
             quote! {
-                let mut #key_var_name = [0u8; #key_size_name + 1];
+                let #key_len_var_name = (#key_size_name as u32).to_le_bytes();
+                let mut #key_var_name = [0u8; #key_size_name];
                 copy_to_array(#key_name.as_bytes(), &mut #key_var_name, #key_size_name);
 
-                let mut #value_var_name = [0u8; #value_size_name + 1];
-                copy_to_array(#value_name.as_bytes(), &mut #value_var_name, #value_size_name);
+                let #value_len_var_name = (#value_size_name as u32).to_le_bytes();
+                let mut #value_var_name = [0u8; #value_size_name];
+                copy_to_array(#value_bytes, &mut #value_var_name, #value_size_name);
             }
         })
         .collect::<Vec<_>>();
@@ -74,36 +124,148 @@ pub(crate) fn emit(items: &[Embossable], options: &EmbossingOptions) -> TokenStr
     let items_field_init_expr = items
         .iter()
         .enumerate()
-        .map(|(index, _)| {
-            let key_name = Ident::new(&format!("key_{}", index), Span::call_site());
-            let value_name = Ident::new(&format!("value_{}", index), Span::call_site());
+        .map(|(index, Embossable { kind, codec, .. })| {
+            let kind_name = ident::kind_field(index);
+            let kind_tag = entry_tag(*kind, *codec);
+            let key_name = ident::key_field(index);
+            let key_len_name = ident::key_len_field(index);
+            let value_name = ident::value_field(index);
+            let value_len_name = ident::value_len_field(index);
 
             quote! {
+                #kind_name: #kind_tag,
+                #key_len_name,
                 #key_name,
+                #value_len_name,
                 #value_name,
             }
         })
         .collect::<Vec<_>>();
 
-    quote! {
-        #[used]
-        #[unsafe(link_section = #stored_in)]
-        pub static EMBOSSED: Embossed = {
+    let (flags, checksum_init_expr, checksum_field_init_expr) = if *checksum {
+        let checksum_init_expr = match checksum_init(items) {
+            Ok(checksum_init_expr) => checksum_init_expr,
+            Err(error_msg) => {
+                return quote! {
+                    const _: () = compile_error!(#error_msg);
+                };
+            }
+        };
+
+        (FLAG_CHECKSUM, checksum_init_expr, quote! { checksum, })
+    } else {
+        (0, quote! {}, quote! {})
+    };
+
+    let (flags, signature_field_init_expr) = if sign_with_env.is_some() {
+        (flags | FLAG_SIGNATURE, quote! { signature: SIGNATURE, })
+    } else {
+        (flags, quote! {})
+    };
+
+    let block_init_expr = quote! {
+        {
             const fn copy_to_array(src: &[u8], dst: &mut [u8], len: usize) {
                 unsafe {
-                    let backing_ptr = dst.as_mut_ptr();
-                    src.as_ptr().copy_to(backing_ptr, len);
-                    *backing_ptr.add(len) = '\0' as u8;
+                    src.as_ptr().copy_to(dst.as_mut_ptr(), len);
                 }
             }
 
             #(#items_init_expr)*
 
+            let field_count = (#items_len as u32).to_le_bytes();
+            #checksum_init_expr
+
             Embossed {
-                leading: #LEADING_MAGIC_BYTES,
-                field_count: #items_len as u8,
+                leading: #magic.to_le_bytes(),
+                marker: #VERSIONED_HEADER_MARKER,
+                version: #FORMAT_VERSION,
+                flags: #flags,
+                field_count,
+                #checksum_field_init_expr
+                #signature_field_init_expr
                 #(#items_field_init_expr)*
             }
+        }
+    };
+
+    if !*as_note {
+        return quote! {
+            #[used]
+            #[unsafe(link_section = #stored_in)]
+            pub static EMBOSSED: Embossed = #block_init_expr;
         };
     }
+
+    // The name is NUL-terminated and padded to a multiple of four bytes, like the descriptor
+    let note_name_size = NOTE_NAME.len() + 1;
+    let mut note_name = NOTE_NAME.as_bytes().to_vec();
+    note_name.resize(note_name_size.next_multiple_of(4), 0);
+    let note_name_size = note_name_size as u32;
+
+    quote! {
+        #[used]
+        #[unsafe(link_section = #stored_in)]
+        pub static NOTE: EmbossedNote = EmbossedNote {
+            name_size: #note_name_size,
+            desc_size: ::core::mem::size_of::<Embossed>() as u32,
+            kind: #NOTE_TYPE,
+            name: [#(#note_name),*],
+            desc: #block_init_expr,
+            padding: [0; NOTE_PADDING],
+        };
+
+        pub static EMBOSSED: &Embossed = &NOTE.desc;
+    }
+}
+
+/// Initialize the checksum of the block, from the values known while expanding if possible
+///
+/// Values such as `include_bytes!` are only read by the compiler, so their checksum has to
+/// be computed by `emboss` as the block is initialized.
+fn checksum_init(items: &[Embossable]) -> Result<TokenStream2, String> {
+    let item = match covered_bytes(items) {
+        Ok(bytes) => {
+            let checksum = Crc32::new().update(&bytes).finish();
+            return Ok(quote! {
+                let checksum = #checksum.to_le_bytes();
+            });
+        }
+        Err(item) => item,
+    };
+
+    let Some(emboss) = emboss_crate() else {
+        return Err(format!(
+            "The value of '{}' is only known after expansion, so its checksum needs the emboss crate. Add emboss as a dependency, or set 'checksum = false'.",
+            item.key
+        ));
+    };
+
+    let checksum_update_expr = items
+        .iter()
+        .enumerate()
+        .map(|(index, Embossable { kind, codec, .. })| {
+            let kind_tag = entry_tag(*kind, *codec);
+            let key_name = ident::key_field(index);
+            let key_len_name = ident::key_len_field(index);
+            let value_name = ident::value_field(index);
+            let value_len_name = ident::value_len_field(index);
+
+            quote! {
+                .update(&[#kind_tag])
+                .update(&#key_len_name)
+                .update(&#key_name)
+                .update(&#value_len_name)
+                .update(&#value_name)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(quote! {
+        let checksum = #emboss::Crc32::new()
+            .update(&field_count)
+            #(#checksum_update_expr)*
+            .finish()
+            .to_le_bytes();
+    })
 }
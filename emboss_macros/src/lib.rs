@@ -13,10 +13,47 @@ use proc_macro::TokenStream;
 ///
 /// # Options
 /// - `key` (required): A string literal specifying the identifier for your embossed value.
-/// - `value` (required): A string literal containing the value to embed.
+/// - `value` (required): The value to embed. Either a string literal, or a typed value:
+///   an integer literal (`42`, `-7i64`), a boolean literal, `Timestamp(<unix seconds>)`, or
+///   raw bytes given as a byte string literal (`b"..."`) or `include_bytes!("path")`.
 /// - `variant_name` (optional): Customize the enum variant name when using the generated enum.
-/// - `stored_in` (optional): Specify the section where the embossed data will be stored. Default is `.emboss.meta`.
+/// - `stored_in` (optional): Specify the section where the embossed data will be stored. Default is `.emboss.meta`. Blocks stored in `emboss_meta` are found at runtime by `emboss::runtime::all_blocks`.
 /// - `export_name` (optional): Create a module with the specified name to access embossed data via a public API.
+/// - `checksum` (optional): Whether to store a CRC-32 of the block, which is verified on extraction. Default is `true`.
+/// - `sign_with_env` (optional): The environment variable holding a hex-encoded Ed25519 secret key to sign the block with. Requires the `signatures` feature.
+/// - `compress` (optional): Compress values with the given codec, currently only `"deflate"`, wherever that makes them smaller. Can't be combined with `export_name`. Requires the `deflate` feature.
+/// - `magic` (optional): The magic number the block starts with, such as `0x5EC7_10A7`, so your blocks can be told apart from anyone else's. Its lowest byte can't be zero. Default is `emboss::LEADING_MAGIC_BYTES`.
+/// - `as_note` (optional): Wrap the block in an ELF note, stored in `.note.emboss` unless `stored_in` names another `.note` section, so it's found through the program headers even once the section headers are stripped. Not supported on macOS. Default is `false`.
+///
+/// # Typed Values
+/// ```rust
+/// # use emboss_macros::*;
+/// emboss!(key = "build-number", value = 42u64, export_name = "build_number");
+///
+/// assert_eq!(build_number::EMBOSSED.get_u64("build-number"), Some(42));
+/// ```
+///
+/// The typed accessors (`get_typed_by_key`, `get_u64`, etc.) return an `emboss::EmbossValue`,
+/// so they're only generated when the crate depends on `emboss`, even if it's renamed.
+///
+/// Byte values are read back with `get_bytes`, as they needn't be UTF-8. The string accessors
+/// leave them out: `get_by_key` and `get_by_index` return `None` for them, and in blocks
+/// holding byte values `get_by_kind` returns an `Option` that is `None` for them too.
+///
+/// ```rust
+/// # use emboss_macros::*;
+/// emboss_many!(
+///     items = [
+///         { key = "pubkey", value = b"\xDE\xAD\xBE\xEF", variant_name = "PubKey" },
+///         { key = "name", value = "signer", variant_name = "Name" },
+///     ],
+///     export_name = "keys"
+/// );
+///
+/// assert_eq!(keys::EMBOSSED.get_bytes("pubkey"), Some(&b"\xDE\xAD\xBE\xEF"[..]));
+/// assert_eq!(keys::EMBOSSED.get_by_key("pubkey"), None);
+/// assert_eq!(keys::EMBOSSED.get_by_kind(keys::EmbossedKeyKind::Name), Some(("name", "signer")));
+/// ```
 ///
 /// # Extended Example
 /// ```rust
@@ -74,10 +111,16 @@ pub fn emboss(input: TokenStream) -> TokenStream {
 /// # Options
 /// - `items` (required): An array of objects, each with:
 ///   - `key` (required): String identifier for the embossed value.
-///   - `value` (required): String value to embed.
+///   - `value` (required): Value to embed, as a string literal or any of the typed values
+///     accepted by `emboss!`.
 ///   - `variant_name` (optional): Custom enum variant name for this item.
-/// - `stored_in` (optional): Section where embossed data will be stored. Default is `.emboss.meta`.
+/// - `stored_in` (optional): Section where embossed data will be stored. Default is `.emboss.meta`. Blocks stored in `emboss_meta` are found at runtime by `emboss::runtime::all_blocks`.
 /// - `export_name` (optional): Module name for accessing the data via a public API.
+/// - `checksum` (optional): Whether to store a CRC-32 of the block, which is verified on extraction. Default is `true`.
+/// - `sign_with_env` (optional): The environment variable holding a hex-encoded Ed25519 secret key to sign the block with. Requires the `signatures` feature.
+/// - `compress` (optional): Compress values with the given codec, currently only `"deflate"`, wherever that makes them smaller. Can't be combined with `export_name`. Requires the `deflate` feature.
+/// - `magic` (optional): The magic number the block starts with, such as `0x5EC7_10A7`, so your blocks can be told apart from anyone else's. Its lowest byte can't be zero. Default is `emboss::LEADING_MAGIC_BYTES`.
+/// - `as_note` (optional): Wrap the block in an ELF note, stored in `.note.emboss` unless `stored_in` names another `.note` section, so it's found through the program headers even once the section headers are stripped. Not supported on macOS. Default is `false`.
 ///
 /// # Extended Example
 /// ```rust
@@ -100,7 +143,6 @@ pub fn emboss(input: TokenStream) -> TokenStream {
 /// ```
 ///
 /// # Notes
-/// - A maximum of 255 items can be embossed in a single section.
 /// - Each section name must be unique within your codebase.
 /// - When using the enum access method (`get_by_kind`), all keys must have valid variant names.
 ///
@@ -144,8 +186,13 @@ pub fn emboss_many(input: TokenStream) -> TokenStream {
 ///   - Default: Fail at compile time if the variable is not set.
 ///   - `fallback = "empty"`: Use an empty string if the variable is not set.
 ///   - `fallback = { value = "default_value" }`: Use the specified default value.
-/// - `stored_in` (optional): Section where embossed data will be stored. Default is `.emboss.meta`.
+/// - `stored_in` (optional): Section where embossed data will be stored. Default is `.emboss.meta`. Blocks stored in `emboss_meta` are found at runtime by `emboss::runtime::all_blocks`.
 /// - `export_name` (optional): Module name for accessing the data via a public API.
+/// - `checksum` (optional): Whether to store a CRC-32 of the block, which is verified on extraction. Default is `true`.
+/// - `sign_with_env` (optional): The environment variable holding a hex-encoded Ed25519 secret key to sign the block with. Requires the `signatures` feature.
+/// - `compress` (optional): Compress values with the given codec, currently only `"deflate"`, wherever that makes them smaller. Can't be combined with `export_name`. Requires the `deflate` feature.
+/// - `magic` (optional): The magic number the block starts with, such as `0x5EC7_10A7`, so your blocks can be told apart from anyone else's. Its lowest byte can't be zero. Default is `emboss::LEADING_MAGIC_BYTES`.
+/// - `as_note` (optional): Wrap the block in an ELF note, stored in `.note.emboss` unless `stored_in` names another `.note` section, so it's found through the program headers even once the section headers are stripped. Not supported on macOS. Default is `false`.
 ///
 /// # Extended Example
 /// ```rust
@@ -209,8 +256,13 @@ pub fn emboss_env(input: TokenStream) -> TokenStream {
 ///   - `key` (optional): Custom key for this variable. Defaults to the environment variable name.
 ///   - `variant_name` (optional): Custom enum variant name.
 ///   - `fallback` (optional): Behavior when the variable is not set (same options as `emboss_env!`).
-/// - `stored_in` (optional): Section where embossed data will be stored. Default is `.emboss.meta`.
+/// - `stored_in` (optional): Section where embossed data will be stored. Default is `.emboss.meta`. Blocks stored in `emboss_meta` are found at runtime by `emboss::runtime::all_blocks`.
 /// - `export_name` (optional): Module name for accessing the data via a public API.
+/// - `checksum` (optional): Whether to store a CRC-32 of the block, which is verified on extraction. Default is `true`.
+/// - `sign_with_env` (optional): The environment variable holding a hex-encoded Ed25519 secret key to sign the block with. Requires the `signatures` feature.
+/// - `compress` (optional): Compress values with the given codec, currently only `"deflate"`, wherever that makes them smaller. Can't be combined with `export_name`. Requires the `deflate` feature.
+/// - `magic` (optional): The magic number the block starts with, such as `0x5EC7_10A7`, so your blocks can be told apart from anyone else's. Its lowest byte can't be zero. Default is `emboss::LEADING_MAGIC_BYTES`.
+/// - `as_note` (optional): Wrap the block in an ELF note, stored in `.note.emboss` unless `stored_in` names another `.note` section, so it's found through the program headers even once the section headers are stripped. Not supported on macOS. Default is `false`.
 ///
 /// # Extended Example
 /// ```rust
@@ -239,7 +291,6 @@ pub fn emboss_env(input: TokenStream) -> TokenStream {
 /// ```
 ///
 /// # Notes
-/// - A maximum of 255 items can be embossed in a single section.
 /// - Each section name must be unique within your codebase.
 /// - Keys used for embossing should be valid Rust identifiers if you intend to use them with enum variants.
 ///
@@ -271,3 +322,44 @@ pub fn emboss_env(input: TokenStream) -> TokenStream {
 pub fn emboss_envs(input: TokenStream) -> TokenStream {
     macro_impl::emboss_envs(input)
 }
+
+/// Embosses a package metadata note, as read by `systemd-coredump` and distribution tooling.
+///
+/// The note follows <https://systemd.io/ELF_PACKAGE_METADATA/>: a `.note.package` ELF note
+/// owned by `FDO`, holding a JSON object of strings. It describes the binary as a whole, so
+/// this macro should only be called once, in the crate producing it.
+///
+/// # Example
+/// ```rust
+/// # use emboss_macros::*;
+/// emboss_package_note!();
+/// ```
+///
+/// # Options
+/// - `items` (optional): An array of objects, each with a `key` and a string `value`, added to
+///   the note. A key that is already set by default replaces the default value.
+/// - `os_release` (optional): Add `os` and `osVersion`, from the `ID` and `VERSION_ID` in the
+///   os-release file of the build environment, if it has one. The crate is rebuilt whenever
+///   that file changes, but the binary then depends on the machine it was built on. Default is
+///   `false`.
+///
+/// By default, the note holds:
+/// - `type`: `cargo`
+/// - `name` and `version`: The `CARGO_PKG_NAME` and `CARGO_PKG_VERSION` of the crate
+///
+/// # Extended Example
+/// ```rust
+/// # use emboss_macros::*;
+/// emboss_package_note!(items = [
+///     { key = "type", value = "rpm" },
+///     { key = "architecture", value = "x86_64" },
+///     { key = "debugInfoUrl", value = "https://debuginfod.example.com/" },
+/// ]);
+/// ```
+///
+/// # Notes
+/// - ELF notes aren't supported on macOS.
+#[proc_macro]
+pub fn emboss_package_note(input: TokenStream) -> TokenStream {
+    macro_impl::emboss_package_note(input)
+}
@@ -1,4 +1,4 @@
-use emboss_common::EmbossingOptions;
+use emboss_common::{Codec, EmbossingOptions, ValueKind};
 use proc_macro::TokenStream;
 use quote::quote;
 use serde::Deserialize;
@@ -33,7 +33,7 @@ pub(crate) fn emboss_env(input: TokenStream) -> TokenStream {
         Err(err) => return err.to_compile_error().into(),
     };
 
-    let value = match fallback {
+    let value = match &fallback {
         EnvVarFallback::Fail => quote! { env!(#env_var) },
         EnvVarFallback::Empty => quote! {{
             match option_env!(#env_var) {
@@ -49,11 +49,15 @@ pub(crate) fn emboss_env(input: TokenStream) -> TokenStream {
         }},
     };
 
+    let known_value = fallback.known_value(&env_var);
     let key = key.unwrap_or_else(|| env_var.clone());
     emboss_token(
         Embossable {
             key,
             value,
+            kind: ValueKind::Str,
+            codec: Codec::None,
+            known_value,
             variant_name,
         },
         options,
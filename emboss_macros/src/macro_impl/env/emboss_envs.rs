@@ -1,4 +1,4 @@
-use emboss_common::EmbossingOptions;
+use emboss_common::{Codec, EmbossingOptions, ValueKind};
 use proc_macro::TokenStream;
 use quote::quote;
 use serde::Deserialize;
@@ -44,6 +44,7 @@ pub(crate) fn emboss_envs(input: TokenStream) -> TokenStream {
     } in env_vars
     {
         let key = key.unwrap_or_else(|| env_var.clone());
+        let known_value = fallback.known_value(&env_var);
         let value = match fallback {
             EnvVarFallback::Fail => quote! { env!(#env_var) },
             EnvVarFallback::Empty => quote! {{
@@ -63,6 +64,9 @@ pub(crate) fn emboss_envs(input: TokenStream) -> TokenStream {
         pairs.push(Embossable {
             key,
             value,
+            kind: ValueKind::Str,
+            codec: Codec::None,
+            known_value,
             variant_name,
         });
     }
@@ -6,15 +6,27 @@ mod emboss_envs;
 pub(crate) use emboss_env::*;
 pub(crate) use emboss_envs::*;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Default)]
 enum EnvVarFallback {
+    #[default]
     Fail,
     Empty,
     Value(String),
 }
 
-impl Default for EnvVarFallback {
-    fn default() -> Self {
-        Self::Fail
+impl EnvVarFallback {
+    /// Resolve the variable the same way the generated `env!` or `option_env!` will
+    ///
+    /// The macro is expanded within the same compiler invocation as the code it generates,
+    /// so both see the same environment.
+    fn known_value(&self, env_var: &str) -> Option<Vec<u8>> {
+        let value = match (std::env::var(env_var), self) {
+            (Ok(value), _) => value,
+            (Err(_), EnvVarFallback::Fail) => return None,
+            (Err(_), EnvVarFallback::Empty) => String::new(),
+            (Err(_), EnvVarFallback::Value(fallback)) => fallback.clone(),
+        };
+
+        Some(value.into_bytes())
     }
 }
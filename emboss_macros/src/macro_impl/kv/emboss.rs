@@ -1,16 +1,20 @@
-use emboss_common::EmbossingOptions;
+use emboss_common::{Codec, EmbossingOptions};
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
-use quote::quote;
 use serde::Deserialize;
+use serde_tokenstream::ParseWrapper;
+use syn::Expr;
 
-use crate::{codegen::emboss_token, macro_impl::Embossable};
+use crate::{
+    codegen::emboss_token,
+    macro_impl::{Embossable, value::typed_value},
+};
 
 #[derive(Deserialize)]
 struct SingleKeyValueEmbossing {
     key: String,
 
-    value: String,
+    value: ParseWrapper<Expr>,
 
     variant_name: Option<String>,
 
@@ -30,10 +34,18 @@ pub(crate) fn emboss(input: TokenStream) -> TokenStream {
         Err(err) => return err.to_compile_error().into(),
     };
 
+    let (value, kind, known_value) = match typed_value(&value) {
+        Ok(val) => val,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
     emboss_token(
         Embossable {
             key,
-            value: quote! { #value },
+            value,
+            kind,
+            codec: Codec::None,
+            known_value,
             variant_name,
         },
         options,
@@ -1,10 +1,14 @@
-use emboss_common::EmbossingOptions;
+use emboss_common::{Codec, EmbossingOptions};
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
-use quote::quote;
 use serde::Deserialize;
+use serde_tokenstream::ParseWrapper;
+use syn::Expr;
 
-use crate::{codegen::emboss_token_multiple, macro_impl::Embossable};
+use crate::{
+    codegen::emboss_token_multiple,
+    macro_impl::{Embossable, value::typed_value},
+};
 
 #[derive(Deserialize)]
 struct MultipleKeyValueEmbossing {
@@ -18,7 +22,7 @@ struct MultipleKeyValueEmbossing {
 struct KeyValueSpec {
     key: String,
 
-    value: String,
+    value: ParseWrapper<Expr>,
 
     variant_name: Option<String>,
 }
@@ -38,9 +42,17 @@ pub(crate) fn emboss_many(input: TokenStream) -> TokenStream {
         variant_name,
     } in p
     {
+        let (value, kind, known_value) = match typed_value(&value) {
+            Ok(val) => val,
+            Err(err) => return err.to_compile_error().into(),
+        };
+
         pairs.push(Embossable {
             key,
-            value: quote! { #value },
+            value,
+            kind,
+            codec: Codec::None,
+            known_value,
             variant_name,
         });
     }
@@ -1,12 +1,20 @@
 mod env;
 mod kv;
+mod package_note;
+mod value;
 
+use emboss_common::{Codec, ValueKind};
 pub(crate) use env::*;
 pub(crate) use kv::*;
+pub(crate) use package_note::*;
 use proc_macro2::TokenStream as TokenStream2;
 
 pub(crate) struct Embossable {
     pub key: String,
     pub value: TokenStream2,
+    pub kind: ValueKind,
+    pub codec: Codec,
+    /// The bytes of the value, if they can be determined while expanding the macro
+    pub known_value: Option<Vec<u8>>,
     pub variant_name: Option<String>,
 }
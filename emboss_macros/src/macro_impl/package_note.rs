@@ -0,0 +1,118 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use serde::Deserialize;
+
+use crate::codegen::package_note_token;
+
+#[derive(Deserialize)]
+struct PackageNote {
+    #[serde(default)]
+    items: Vec<PackageNoteSpec>,
+
+    #[serde(default)]
+    os_release: bool,
+}
+
+#[derive(Deserialize)]
+struct PackageNoteSpec {
+    key: String,
+
+    value: String,
+}
+
+pub(crate) fn emboss_package_note(input: TokenStream) -> TokenStream {
+    let input = TokenStream2::from(input);
+    let PackageNote { items, os_release } =
+        match serde_tokenstream::from_tokenstream::<PackageNote>(&input) {
+            Ok(val) => val,
+            Err(err) => return err.to_compile_error().into(),
+        };
+
+    let mut pairs = default_pairs();
+    let os_release = match os_release {
+        true => read_os_release(&mut pairs),
+        false => None,
+    };
+    let mut given = Vec::with_capacity(items.len());
+    for PackageNoteSpec { key, value } in items {
+        if given.contains(&key) {
+            let error_msg = format!("The key '{}' is given more than once.", key);
+            return quote! {
+                const _: () = compile_error!(#error_msg);
+            }
+            .into();
+        }
+
+        // Keys given explicitly replace the defaults, wherever those were
+        match pairs.iter_mut().find(|(pair_key, _)| *pair_key == key) {
+            Some((_, pair_value)) => *pair_value = value,
+            None => pairs.push((key.clone(), value)),
+        }
+
+        given.push(key);
+    }
+
+    let note = package_note_token(&pairs);
+
+    // Including the file makes cargo rebuild the crate whenever it changes, which it can't
+    // know about from the macro reading it
+    let track = os_release.map(|path| {
+        quote! {
+            const _: &str = include_str!(#path);
+        }
+    });
+
+    quote! {
+        #track
+        #note
+    }
+    .into()
+}
+
+/// The package type and the `CARGO_PKG_NAME` and `CARGO_PKG_VERSION` of the crate
+fn default_pairs() -> Vec<(String, String)> {
+    let mut pairs = vec![("type".to_string(), "cargo".to_string())];
+    for (key, env_var) in [("name", "CARGO_PKG_NAME"), ("version", "CARGO_PKG_VERSION")] {
+        if let Ok(value) = std::env::var(env_var) {
+            pairs.push((key.to_string(), value));
+        }
+    }
+
+    pairs
+}
+
+/// Add the OS being built on, returning the path of the os-release file it was read from
+///
+/// Like the package note tooling of distributions, the OS comes from the `ID` and
+/// `VERSION_ID` of the os-release file of the build environment, and is left out if there is
+/// none.
+fn read_os_release(pairs: &mut Vec<(String, String)>) -> Option<&'static str> {
+    let (path, os_release) = ["/etc/os-release", "/usr/lib/os-release"]
+        .into_iter()
+        .find_map(|path| Some((path, std::fs::read_to_string(path).ok()?)))?;
+    for (key, field) in [("os", "ID"), ("osVersion", "VERSION_ID")] {
+        if let Some(value) = os_release_field(&os_release, field) {
+            pairs.push((key.to_string(), value));
+        }
+    }
+
+    Some(path)
+}
+
+fn os_release_field(os_release: &str, field: &str) -> Option<String> {
+    os_release.lines().find_map(|line| {
+        let value = line.strip_prefix(field)?.strip_prefix('=')?.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .or_else(|| {
+                value
+                    .strip_prefix('\'')
+                    .and_then(|value| value.strip_suffix('\''))
+            })
+            .unwrap_or(value);
+
+        Some(value.to_string())
+    })
+}
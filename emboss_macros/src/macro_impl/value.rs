@@ -0,0 +1,100 @@
+use emboss_common::ValueKind;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{ToTokens, quote};
+use syn::{Expr, ExprCall, ExprLit, ExprMacro, ExprUnary, Lit, LitInt, UnOp};
+
+/// Convert the expression given as a `value` into a string expression, the kind of value
+/// it holds, and the bytes it will be embossed as when they're known during expansion
+///
+/// Typed values are embossed as their canonical text, so the text is computed here. Byte
+/// values are instead given as a byte slice expression.
+pub(crate) fn typed_value(expr: &Expr) -> syn::Result<(TokenStream2, ValueKind, Option<Vec<u8>>)> {
+    let (text, kind) = match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(lit), ..
+        }) => {
+            let bytes = lit.value().into_bytes();
+            return Ok((quote! { #lit }, ValueKind::Str, Some(bytes)));
+        }
+        Expr::Lit(ExprLit {
+            lit: Lit::ByteStr(lit),
+            ..
+        }) => return Ok((quote! { #lit }, ValueKind::Bytes, Some(lit.value()))),
+        // The file is read by the compiler after expansion, so its contents aren't known here
+        Expr::Macro(ExprMacro { mac, .. }) if mac.path.is_ident("include_bytes") => {
+            return Ok((quote! { #mac }, ValueKind::Bytes, None));
+        }
+        Expr::Lit(ExprLit {
+            lit: Lit::Bool(lit),
+            ..
+        }) => (lit.value.to_string(), ValueKind::Bool),
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(lit), ..
+        }) => int_value(lit, false)?,
+        Expr::Unary(ExprUnary {
+            op: UnOp::Neg(_),
+            expr,
+            ..
+        }) => match expr.as_ref() {
+            Expr::Lit(ExprLit {
+                lit: Lit::Int(lit), ..
+            }) => int_value(lit, true)?,
+            _ => return Err(unsupported(expr)),
+        },
+        Expr::Call(call) => timestamp_value(call)?,
+        _ => return Err(unsupported(expr)),
+    };
+
+    let bytes = text.clone().into_bytes();
+    Ok((quote! { #text }, kind, Some(bytes)))
+}
+
+fn int_value(lit: &LitInt, negative: bool) -> syn::Result<(String, ValueKind)> {
+    let signed = match lit.suffix() {
+        "" | "u8" | "u16" | "u32" | "u64" | "usize" => negative,
+        "i8" | "i16" | "i32" | "i64" | "isize" => true,
+        _ => {
+            return Err(syn::Error::new(
+                lit.span(),
+                "Only integers that fit in a u64 or i64 can be embossed",
+            ));
+        }
+    };
+
+    if !signed {
+        let value = lit.base10_parse::<u64>()?;
+        return Ok((value.to_string(), ValueKind::U64));
+    }
+
+    // Parsing the magnitude as an i128 lets i64::MIN through without overflowing
+    let magnitude = lit.base10_parse::<i128>()?;
+    let value = if negative { -magnitude } else { magnitude };
+    let value = i64::try_from(value)
+        .map_err(|_| syn::Error::new(lit.span(), "Integer does not fit in an i64"))?;
+
+    Ok((value.to_string(), ValueKind::I64))
+}
+
+fn timestamp_value(call: &ExprCall) -> syn::Result<(String, ValueKind)> {
+    let is_timestamp =
+        matches!(call.func.as_ref(), Expr::Path(path) if path.path.is_ident("Timestamp"));
+    let seconds = match (is_timestamp, call.args.first(), call.args.len()) {
+        (
+            true,
+            Some(Expr::Lit(ExprLit {
+                lit: Lit::Int(lit), ..
+            })),
+            1,
+        ) => lit.base10_parse::<u64>()?,
+        _ => return Err(unsupported(&Expr::Call(call.clone()))),
+    };
+
+    Ok((seconds.to_string(), ValueKind::Timestamp))
+}
+
+fn unsupported(expr: &Expr) -> syn::Error {
+    syn::Error::new_spanned(
+        expr.to_token_stream(),
+        "Expected a string, byte string, integer, or boolean literal, include_bytes!, or Timestamp(<seconds>)",
+    )
+}
@@ -0,0 +1,31 @@
+//! Builds emboss without `std` or `alloc`, as firmware would
+//!
+//! Nothing here runs; building it for a bare-metal target such as `thumbv7em-none-eabihf`
+//! is the test.
+#![no_std]
+
+use emboss::{emboss, emboss_many, extract};
+
+emboss!(key = "firmware-version", value = "1.2.3");
+
+emboss_many!(
+    items = [
+        { key = "board", value = "rev-c" },
+        { key = "build-number", value = 42u64 },
+    ],
+    stored_in = "build",
+    export_name = "BUILD"
+);
+
+/// Find the value of a key in the embossed section
+pub fn lookup<'a>(section: &'a [u8], key: &str) -> Option<&'a str> {
+    extract::iter(section)
+        .map_while(Result::ok)
+        .find(|(candidate, _)| *candidate == key)
+        .map(|(_, value)| value)
+}
+
+/// Read the embossed build number straight from the exported block
+pub fn build_number() -> Option<u64> {
+    BUILD::EMBOSSED.get_u64("build-number")
+}
@@ -2,4 +2,9 @@ fn main() {
     println!("cargo:rustc-env=env-emboss-var=4");
     println!("cargo:rustc-env=many-env-emboss-var-1=5");
     println!("cargo:rustc-env=many-env-emboss-var-3=6");
+
+    // The secret key from the first Ed25519 test vector of RFC 8032, never use it for real
+    println!(
+        "cargo:rustc-env=EMBOSS_TEST_SIGNING_KEY=9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60"
+    );
 }
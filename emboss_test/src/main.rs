@@ -1,7 +1,12 @@
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap};
 
-use emboss::{emboss, emboss_env, emboss_envs, emboss_many};
-use object::{Object, ObjectSection};
+use emboss::{
+    Codec, EmbossValue, emboss, emboss_env, emboss_envs, emboss_many,
+    encode::BlockBuilder,
+    error::{EmbossError, ErrorKind},
+    extract::{ExtractOptions, Magic},
+    read::{EmbossedSection, ReadOptions},
+};
 
 emboss!(key = "regular-emboss", value = "1");
 
@@ -19,6 +24,93 @@ emboss_env!(
     stored_in = "elsewhere"
 );
 
+emboss_many!(
+    items = [
+        { key = "blob", value = include_bytes!("../blob.bin") },
+        { key = "build-number", value = 42u64 },
+    ],
+    stored_in = "typed",
+    export_name = "typed"
+);
+
+emboss_many!(
+    items = [
+        { key = "version", value = "1.2.3" },
+        { key = "build-number", value = 42u64 },
+    ],
+    stored_in = "signed",
+    sign_with_env = "EMBOSS_TEST_SIGNING_KEY"
+);
+
+emboss_env!(
+    env_var = "env-emboss-var",
+    key = "env-emboss",
+    stored_in = "signed_env",
+    sign_with_env = "EMBOSS_TEST_SIGNING_KEY"
+);
+
+emboss_many!(
+    items = [
+        { key = "license", value = "Licensed under either of the Apache License, Version 2.0 or the MIT license, at your option. Unless you explicitly state otherwise, any contribution intentionally submitted for inclusion in the work by you, as defined in the Apache-2.0 license, shall be dual licensed as above, without any additional terms or conditions." },
+        { key = "tiny", value = "1" },
+    ],
+    stored_in = "compressed",
+    compress = "deflate",
+    sign_with_env = "EMBOSS_TEST_SIGNING_KEY"
+);
+
+emboss_many!(
+    items = [
+        { key = "firmware", value = "ours" },
+    ],
+    stored_in = "magic",
+    magic = 0x5EC7_10A7
+);
+
+// Any number of blocks can share the runtime section, across crates too
+emboss!(
+    key = "runtime-emboss",
+    value = "8",
+    stored_in = "emboss_meta"
+);
+
+emboss_many!(
+    items = [
+        { key = "runtime-many-emboss", value = "9" },
+    ],
+    stored_in = "emboss_meta"
+);
+
+// Notes survive the section headers being stripped
+#[cfg(target_os = "linux")]
+emboss!(key = "note-emboss", value = "10", as_note = true);
+
+#[cfg(target_os = "linux")]
+emboss_many!(
+    items = [
+        { key = "note-emboss", value = "11" },
+        { key = "note-many-emboss", value = "12" },
+    ],
+    as_note = true,
+    export_name = "noted_signed",
+    sign_with_env = "EMBOSS_TEST_SIGNING_KEY"
+);
+
+#[cfg(target_os = "linux")]
+emboss::emboss_package_note!(
+    items = [
+        { key = "type", value = "rpm" },
+        { key = "osVersion", value = "39" },
+    ],
+    os_release = true
+);
+
+// The public key matching `EMBOSS_TEST_SIGNING_KEY`, set in the build script
+const PUBLIC_KEY: [u8; 32] = [
+    0xd7, 0x5a, 0x98, 0x01, 0x82, 0xb1, 0x0a, 0xb7, 0xd5, 0x4b, 0xfe, 0xd3, 0xc9, 0x64, 0x07, 0x3a,
+    0x0e, 0xe1, 0x72, 0xf3, 0xda, 0xa6, 0x23, 0x25, 0xaf, 0x02, 0x1a, 0x68, 0xf7, 0x07, 0x51, 0x1a,
+];
+
 emboss_envs!(export_name = ManyEnvVars, env_vars = [
     { env_var = "many-env-emboss-var-1" },
     { env_var = "many-env-emboss-var-2", fallback = Empty },
@@ -27,18 +119,16 @@ emboss_envs!(export_name = ManyEnvVars, env_vars = [
 ], stored_in = "nowhere");
 
 fn main() {
-    let self_path = std::env::current_exe().expect("failed to get current path");
-    let file = std::fs::read(self_path).expect("could not read file");
-    let file = object::File::parse(&*file).expect("failed to parse object file");
+    let sections = emboss::read::read_current_exe().expect("should be able to read sections");
 
-    let metadata = get_section_data(&file, emboss::DEFAULT_SECTION_NAME);
+    let metadata = get_section_data(&sections, emboss::DEFAULT_SECTION_NAME);
 
     let value = *metadata
         .get("regular-emboss")
         .expect("regular-emboss should be present");
     assert_eq!(value, "1");
 
-    let metadata = get_section_data(&file, "somewhere");
+    let metadata = get_section_data(&sections, "somewhere");
 
     let value = *metadata
         .get("many-emboss-1")
@@ -50,14 +140,21 @@ fn main() {
         .expect("many-emboss-2 should be present");
     assert_eq!(value, "3");
 
-    let metadata = get_section_data(&file, "elsewhere");
+    let section = get_section(&sections, "somewhere");
+    let metadata = section
+        .metadata()
+        .expect("should be able to parse metadata");
+    let json = serde_json::to_string(&metadata).expect("metadata should serialize");
+    assert_eq!(json, r#"{"many-emboss-1":"2","many-emboss-2":"3"}"#);
+
+    let metadata = get_section_data(&sections, "elsewhere");
 
     let value = *metadata
         .get("env-emboss")
         .expect("env-emboss should be present");
     assert_eq!(value, "4");
 
-    let metadata = get_section_data(&file, "nowhere");
+    let metadata = get_section_data(&sections, "nowhere");
 
     let value = *metadata
         .get("many-env-emboss-var-1")
@@ -90,12 +187,203 @@ fn main() {
 
     let field = ManyEnvVars::EMBOSSED.get_by_kind(ManyEnvVars::EmbossedKeyKind::LastEmbossVar);
     assert_eq!(field, ("many-env-emboss-4", "7"));
+
+    let blob = include_bytes!("../blob.bin");
+    assert_eq!(typed::EMBOSSED.get_bytes("blob"), Some(&blob[..]));
+    assert_eq!(typed::EMBOSSED.get_by_key("blob"), None);
+    assert_eq!(typed::EMBOSSED.get_u64("build-number"), Some(42));
+
+    let section = get_section(&sections, "typed");
+    let data = &section.data;
+    let metadata = emboss::extract::extract_typed_metadata_into_hashmap(data)
+        .expect("should be able to parse metadata");
+    assert_eq!(metadata.get("blob"), Some(&EmbossValue::Bytes(blob)));
+    assert_eq!(metadata.get("build-number"), Some(&EmbossValue::U64(42)));
+
+    for section_name in ["signed", "signed_env"] {
+        let section = get_section(&sections, section_name);
+        let data = &section.data;
+        emboss::extract::verify(data, &PUBLIC_KEY).expect("signature should be valid");
+
+        let mut other_key = PUBLIC_KEY;
+        other_key[0] ^= 1;
+        assert_ne!(emboss::extract::verify(data, &other_key), Ok(()));
+    }
+
+    let metadata = get_section_data(&sections, "signed");
+    assert_eq!(metadata.get("version"), Some(&"1.2.3"));
+    assert_eq!(metadata.get("build-number"), Some(&"42"));
+
+    let section = get_section(&sections, "compressed");
+    let data = &section.data;
+    emboss::extract::verify(data, &PUBLIC_KEY).expect("signature should be valid");
+    let err = emboss::extract::extract_metadata_into_vec(data).expect_err("should be compressed");
+    assert!(matches!(err.kind(), ErrorKind::CompressedValue(_)));
+    assert_eq!(err.key(), Some("license"));
+
+    let metadata = emboss::extract::extract_decompressed_metadata_into_hashmap(data)
+        .expect("should be able to parse metadata");
+    let license = metadata.get("license").expect("license should be present");
+    assert!(matches!(license, Cow::Owned(_)));
+    assert!(license.starts_with("Licensed under either of"));
+    assert!(data.len() < license.len());
+    assert_eq!(metadata.get("tiny"), Some(&Cow::Borrowed("1")));
+
+    // The runtime encoder produces the exact same block as the macro
+    let block = BlockBuilder::new()
+        .str("license", license)
+        .str("tiny", "1")
+        .compress(Codec::Deflate)
+        .sign_with(&secret_key())
+        .build()
+        .expect("should be able to build a block");
+    assert_eq!(&data[..block.len()], block);
+    assert!(data[block.len()..].iter().all(|byte| *byte == 0));
+
+    // Blocks embossed with a custom magic number are only read when asked for
+    assert!(sections.iter().all(|section| section.name != "magic"));
+    let options = ReadOptions {
+        extract: ExtractOptions {
+            magic: Magic::Exactly(0x5EC7_10A7),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let custom = emboss::read::read_current_exe_with_options(&options)
+        .expect("should be able to read sections");
+    assert_eq!(custom.len(), 1);
+    let section = get_section(&custom, "magic");
+    assert_eq!(section.data[..4], 0x5EC7_10A7u32.to_le_bytes());
+    assert!(emboss::extract::extract_metadata_into_vec(&section.data).is_err());
+    let metadata = section.iter().collect::<Result<Vec<_>, _>>();
+    assert_eq!(metadata, Ok(vec![("firmware", "ours")]));
+
+    #[cfg(target_os = "linux")]
+    check_elf();
+
+    // Unsigned sections are rejected outright
+    let section = get_section(&sections, "typed");
+    let data = &section.data;
+    assert_eq!(
+        emboss::extract::verify(data, &PUBLIC_KEY).map_err(EmbossError::into_kind),
+        Err(ErrorKind::MissingSignature)
+    );
+}
+
+#[cfg(target_os = "linux")]
+fn check_elf() {
+    // Blocks in the runtime section are found in memory, without reading the executable
+    let mut runtime = emboss::runtime::all_blocks()
+        .map(|block| block.expect("block should be valid"))
+        .flat_map(|block| block.iter())
+        .collect::<Result<Vec<_>, _>>()
+        .expect("should be able to parse metadata");
+    runtime.sort();
+    assert_eq!(
+        runtime,
+        [("runtime-emboss", "8"), ("runtime-many-emboss", "9")]
+    );
+
+    // As are the notes of every loaded object
+    let exe = std::env::current_exe().expect("should know the executable");
+    let modules = emboss::runtime::loaded_modules();
+    let module = modules
+        .iter()
+        .find(|module| module.path == exe)
+        .expect("the executable should be loaded");
+    let pairs = module
+        .blocks()
+        .flat_map(|block| block.expect("block should be valid").iter().flatten())
+        .collect::<Vec<_>>();
+    assert!(pairs.contains(&("note-emboss", "10")));
+    assert!(pairs.contains(&("note-many-emboss", "12")));
+    assert!(!pairs.contains(&("regular-emboss", "1")));
+
+    // Notes are found through the program headers, even once the section headers are gone
+    let data = std::fs::read(&exe).expect("should be able to read the executable");
+    let stripped = strip_section_headers(data);
+    let sections = emboss::read::read_bytes(&stripped).expect("should be able to read sections");
+    assert!(sections.iter().all(|section| section.name != "somewhere"));
+
+    let mut noted = sections
+        .iter()
+        .map(|section| {
+            assert_eq!(section.name, emboss::NOTE_SECTION_NAME);
+            section
+                .metadata()
+                .expect("should be able to parse metadata")
+        })
+        .collect::<Vec<_>>();
+    noted.sort_by_key(|metadata| metadata.get("note-emboss").map(str::to_string));
+    assert_eq!(noted.len(), 2);
+    assert_eq!(noted[0].get("note-emboss"), Some("10"));
+    assert_eq!(noted[1].get("note-emboss"), Some("11"));
+    assert_eq!(noted[1].get("note-many-emboss"), Some("12"));
+    assert_eq!(
+        noted_signed::EMBOSSED.get_by_key("note-emboss"),
+        Some(("note-emboss", "11"))
+    );
+
+    let section = sections
+        .iter()
+        .find(|section| {
+            section
+                .metadata()
+                .is_ok_and(|m| m.get("note-many-emboss").is_some())
+        })
+        .expect("signed note should be present");
+    emboss::extract::verify(&section.data, &PUBLIC_KEY).expect("signature should be valid");
+
+    // As is the package metadata note
+    let metadata = emboss::read::read_package_note_bytes(&stripped)
+        .expect("should be able to read the package note")
+        .expect("package note should be present");
+    assert_eq!(metadata.get("type"), Some("rpm"));
+    assert_eq!(metadata.get("name"), Some("emboss_test"));
+    assert_eq!(metadata.get("version"), Some(env!("CARGO_PKG_VERSION")));
+    assert_eq!(metadata.get("osVersion"), Some("39"));
+
+    // The OS was only read because it was asked for
+    let os_release = ["/etc/os-release", "/usr/lib/os-release"]
+        .iter()
+        .any(|path| std::path::Path::new(path).exists());
+    assert_eq!(metadata.get("os").is_some(), os_release);
+}
+
+/// Drop the section headers of a 64-bit little-endian ELF file, like sstrip or
+/// `strip --strip-section-headers` do
+#[cfg(target_os = "linux")]
+fn strip_section_headers(mut data: Vec<u8>) -> Vec<u8> {
+    let section_headers = u64::from_le_bytes(data[0x28..0x30].try_into().expect("should be ELF"));
+
+    // `e_shoff`, then `e_shnum` and `e_shstrndx`
+    data[0x28..0x30].fill(0);
+    data[0x3C..0x40].fill(0);
+    data.truncate(section_headers as usize);
+    data
+}
+
+fn secret_key() -> [u8; 32] {
+    let hex = env!("EMBOSS_TEST_SIGNING_KEY");
+    let mut key = [0; 32];
+    for (index, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).expect("key should be hex");
+    }
+
+    key
+}
+
+fn get_section<'a>(sections: &'a [EmbossedSection], section_name: &str) -> &'a EmbossedSection {
+    sections
+        .iter()
+        .find(|section| section.name == section_name)
+        .expect("metadata should exist")
 }
 
-fn get_section_data<'a>(file: &'a object::File, section_name: &str) -> HashMap<&'a str, &'a str> {
-    let section = file
-        .section_by_name(section_name)
-        .expect("metadata should exist");
-    let data = section.data().expect("data should be available");
+fn get_section_data<'a>(
+    sections: &'a [EmbossedSection],
+    section_name: &str,
+) -> HashMap<&'a str, &'a str> {
+    let data = &get_section(sections, section_name).data;
     emboss::extract::extract_metadata_into_hashmap(data).expect("should be able to parse metadata")
 }
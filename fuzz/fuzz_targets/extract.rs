@@ -0,0 +1,71 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use emboss::extract::{self, DuplicatePolicy, ExtractOptions, Magic};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+struct Input<'a> {
+    strict: bool,
+    max_pairs: Option<u8>,
+    max_string_len: Option<u8>,
+    skip_checksums: bool,
+    duplicates: u8,
+    magic: Option<u32>,
+    data: &'a [u8],
+}
+
+fuzz_target!(|input: Input| {
+    let options = ExtractOptions {
+        strict: input.strict,
+        max_pairs: input.max_pairs.map(usize::from),
+        max_string_len: input.max_string_len.map(usize::from),
+        skip_checksums: input.skip_checksums,
+        duplicates: match input.duplicates % 4 {
+            0 => DuplicatePolicy::CollectAll,
+            1 => DuplicatePolicy::Error,
+            2 => DuplicatePolicy::FirstWins,
+            _ => DuplicatePolicy::LastWins,
+        },
+        magic: match input.magic {
+            Some(magic) => Magic::Exactly(magic),
+            None => Magic::Default,
+        },
+    };
+
+    let pairs = extract::extract_metadata_into_vec_with_options(input.data, &options);
+    let blocks = extract::extract_metadata_into_blocks_with_options(input.data, &options);
+    let decompressed = extract::iter_decompressed_with_options(input.data, &options)
+        .collect::<Result<Vec<_>, _>>();
+    let lossy = extract::extract_metadata_lossy_with_options(input.data, &options);
+
+    // Duplicates are only resolved within each block when grouping, and never by the
+    // iterators, so the rest only holds when every pair is kept
+    if options.duplicates != DuplicatePolicy::CollectAll {
+        return;
+    }
+
+    // Grouping by block must never change what is parsed
+    if let (Ok(pairs), Ok(blocks)) = (&pairs, blocks) {
+        assert_eq!(*pairs, blocks.concat());
+    }
+
+    // Recovering finds everything that parses cleanly, though it may find more blocks
+    // beyond what the strict parse stopped at
+    if let Ok(pairs) = &pairs {
+        let recovered = lossy
+            .pairs
+            .iter()
+            .map(|(key, value)| (key.as_ref(), value.as_ref()));
+        assert!(pairs.iter().copied().eq(recovered.take(pairs.len())));
+    }
+
+    // Without compressed values, decompressing is the same as borrowing
+    if let (Ok(pairs), Ok(decompressed)) = (pairs, decompressed) {
+        let decompressed = decompressed
+            .iter()
+            .map(|(key, value)| (*key, value.as_ref()))
+            .collect::<Vec<_>>();
+        assert_eq!(pairs, decompressed);
+    }
+});